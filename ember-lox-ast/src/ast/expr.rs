@@ -1,10 +1,12 @@
+use super::stmt::Stmt;
 use crate::{
   pool::prelude::*,
   visit::{Visitor, VisitorAcceptor},
   STR,
 };
-use ember_lox_tokenizer::TokenKind;
+use ember_lox_tokenizer::{Span, TokenKind};
 use std::{
+  cell::Cell,
   fmt::{Debug, Display},
   sync::Arc,
 };
@@ -14,63 +16,204 @@ pub enum Expr {
   Assign {
     name: STR,
     val: Box<Expr>,
+    depth: ScopeDepth,
+    span: Span,
   },
   Binary {
     left: Box<Expr>,
     op: PosedOperator,
     right: Box<Expr>,
+    span: Span,
   },
   Call {
     callee: Box<Expr>,
     args: Vec<Expr>,
+    span: Span,
   },
   Get {
     obj: Box<Expr>,
     name: STR,
+    span: Span,
   },
   Grouping {
     expr: Box<Expr>,
+    span: Span,
+  },
+  /// `"Hello ${name}!"`, where `parts` are the literal segments and embedded expressions.
+  Interpolation {
+    parts: Vec<Expr>,
+    span: Span,
+  },
+  /// `obj[index]`
+  Index {
+    obj: Box<Expr>,
+    index: Box<Expr>,
+    span: Span,
+  },
+  /// `fun (a, b) { ... }`, an anonymous function.
+  Lambda {
+    params: Vec<STR>,
+    body: Vec<Stmt>,
+    span: Span,
+  },
+  /// `[a, b, c]`
+  List {
+    elements: Vec<Expr>,
+    span: Span,
+  },
+  /// `{"a": 1, "b": 2}`, with the entries in the written order.
+  Map {
+    entries: Vec<(Expr, Expr)>,
+    span: Span,
   },
   Literal {
     val: PosedLiteral,
+    span: Span,
   },
   Logical {
     left: Box<Expr>,
     op: PosedOperator,
     right: Box<Expr>,
+    span: Span,
   },
   Set {
     obj: Box<Expr>,
     name: STR,
     val: Box<Expr>,
+    span: Span,
+  },
+  /// `obj[index] = val`
+  SetIndex {
+    obj: Box<Expr>,
+    index: Box<Expr>,
+    val: Box<Expr>,
+    span: Span,
+  },
+  /// `cond ? then_branch : else_branch`
+  Ternary {
+    cond: Box<Expr>,
+    then_branch: Box<Expr>,
+    else_branch: Box<Expr>,
+    span: Span,
   },
   Super {
     keyword: STR,
     method: STR,
+    depth: ScopeDepth,
+    span: Span,
   },
   This {
     keyword: STR,
+    depth: ScopeDepth,
+    span: Span,
   },
   Unary {
     op: PosedOperator,
     right: Box<Expr>,
+    span: Span,
+  },
+  /// `target op= val`, or `++target` and `target++` (`is_postfix`) when `val` is `None`.
+  ///
+  /// The `target` is an [Expr::Var], [Expr::Get] or [Expr::Index], whose operands are
+  /// evaluated only once. A postfix one evaluates to the value before the update.
+  Update {
+    target: Box<Expr>,
+    op: PosedOperator,
+    val: Option<Box<Expr>>,
+    is_postfix: bool,
+    span: Span,
   },
   Var {
     name: STR,
+    depth: ScopeDepth,
+    span: Span,
   },
 }
 
+impl Expr {
+  /// The span of the whole expression.
+  pub fn span(&self) -> Span {
+    use Expr::*;
+    match self {
+      Assign { span, .. }
+      | Binary { span, .. }
+      | Call { span, .. }
+      | Get { span, .. }
+      | Grouping { span, .. }
+      | Index { span, .. }
+      | Interpolation { span, .. }
+      | Lambda { span, .. }
+      | List { span, .. }
+      | Literal { span, .. }
+      | Map { span, .. }
+      | Logical { span, .. }
+      | Set { span, .. }
+      | SetIndex { span, .. }
+      | Super { span, .. }
+      | Ternary { span, .. }
+      | This { span, .. }
+      | Unary { span, .. }
+      | Update { span, .. }
+      | Var { span, .. } => *span,
+    }
+  }
+
+  /// The span of the token locating the expression (e.g. the operator of a binary one).
+  ///
+  /// For compound expressions, it's the span of the leading position-aware part.
+  pub fn pos(&self) -> Span {
+    use Expr::*;
+    match self {
+      Assign { name, .. } => name.1,
+      Binary { op, .. } | Logical { op, .. } | Unary { op, .. } | Update { op, .. } => op.1,
+      Call { callee, .. } => callee.pos(),
+      Get { name, .. } | Set { name, .. } => name.1,
+      Grouping { expr, .. } => expr.pos(),
+      Index { index, .. } | SetIndex { index, .. } => index.pos(),
+      Interpolation { span, .. } | Lambda { span, .. } | List { span, .. } | Map { span, .. } => {
+        *span
+      }
+      Ternary { cond, .. } => cond.pos(),
+      Literal { val, .. } => val.1,
+      Super { keyword, .. } | This { keyword, .. } => keyword.1,
+      Var { name, .. } => name.1,
+    }
+  }
+
+  /// The line where the expression is located, see [Expr::pos].
+  pub fn line(&self) -> usize {
+    self.pos().line
+  }
+}
+
 impl VisitorAcceptor for Expr {
   fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Output {
     visitor.visit_expr(self)
   }
 }
 
+/// Number of scopes between a variable use and the scope declaring it.
+///
+/// It's recorded by the resolver after parsing, `None` means the variable is global.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeDepth(Cell<Option<usize>>);
+
+impl ScopeDepth {
+  pub fn get(&self) -> Option<usize> {
+    self.0.get()
+  }
+
+  pub fn set(&self, depth: usize) {
+    self.0.set(Some(depth));
+  }
+}
+
+/// An operator with the [Span] of its token.
 #[derive(Debug, Clone, Copy)]
-pub struct PosedOperator(pub Operator, pub usize);
+pub struct PosedOperator(pub Operator, pub Span);
 
-impl From<(Operator, usize)> for PosedOperator {
-  fn from(value: (Operator, usize)) -> Self {
+impl From<(Operator, Span)> for PosedOperator {
+  fn from(value: (Operator, Span)) -> Self {
     Self(value.0, value.1)
   }
 }
@@ -85,6 +228,10 @@ pub enum Operator {
   Multiply,
   /// /
   Divide,
+  /// %
+  Modulo,
+  /// **
+  Power,
   /// ==
   Equal,
   /// !=
@@ -116,6 +263,8 @@ impl TryFrom<TokenKind> for Operator {
       Minus => Ok(Operator::Minus),
       Star => Ok(Operator::Multiply),
       Slash => Ok(Operator::Divide),
+      Percent => Ok(Operator::Modulo),
+      StarStar => Ok(Operator::Power),
       EqEq => Ok(Operator::Equal),
       BangEq => Ok(Operator::NotEqual),
       Gt => Ok(Operator::Greater),
@@ -123,6 +272,11 @@ impl TryFrom<TokenKind> for Operator {
       Lt => Ok(Operator::Less),
       LtEq => Ok(Operator::LessEqual),
       Bang => Ok(Operator::Not),
+      // The arithmetic of updating ones, e.g. `+=` and `++`.
+      PlusEq | PlusPlus => Ok(Operator::Plus),
+      MinusEq | MinusMinus => Ok(Operator::Minus),
+      StarEq => Ok(Operator::Multiply),
+      SlashEq => Ok(Operator::Divide),
       _ => Err(()),
     }
   }
@@ -136,6 +290,8 @@ impl Display for Operator {
       Minus => "-",
       Multiply => "*",
       Divide => "/",
+      Modulo => "%",
+      Power => "**",
       Equal => "==",
       NotEqual => "!=",
       Greater => ">",
@@ -150,11 +306,12 @@ impl Display for Operator {
   }
 }
 
+/// A literal value with the [Span] of its token.
 #[derive(Debug, Clone)]
-pub struct PosedLiteral(pub LiteralValue, pub usize);
+pub struct PosedLiteral(pub LiteralValue, pub Span);
 
-impl From<(LiteralValue, usize)> for PosedLiteral {
-  fn from(value: (LiteralValue, usize)) -> Self {
+impl From<(LiteralValue, Span)> for PosedLiteral {
+  fn from(value: (LiteralValue, Span)) -> Self {
     Self(value.0, value.1)
   }
 }
@@ -167,13 +324,37 @@ pub enum LiteralValue {
   Nil,
 }
 
+/// An operator is applied to operands of the wrong types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandError {
+  /// The operand of a unary operator should be a number.
+  ExpectNumber,
+  /// Both operands of a binary operator should be numbers.
+  ExpectNumbers,
+}
+
+impl Display for OperandError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      OperandError::ExpectNumber => write!(f, "Operand must be a number."),
+      OperandError::ExpectNumbers => write!(f, "Operands must be numbers."),
+    }
+  }
+}
+
 impl LiteralValue {
-  pub fn check_both_numeric(&self, other: &Self) -> Result<(f64, f64), String> {
+  pub fn check_both_numeric(&self, other: &Self) -> Result<(f64, f64), OperandError> {
     match (self, other) {
       (LiteralValue::Number(a), LiteralValue::Number(b)) => Ok((*a, *b)),
-      _ => Err(format!("Operands must be numbers.")),
+      _ => Err(OperandError::ExpectNumbers),
     }
   }
+
+  /// `self ** rhs`, as there's no operator trait for it.
+  pub fn pow(&self, rhs: &Self) -> Result<LiteralValue, OperandError> {
+    let (a, b) = self.check_both_numeric(rhs)?;
+    Ok(LiteralValue::Number(a.powf(b)))
+  }
 }
 
 impl LiteralValue {
@@ -217,7 +398,7 @@ impl std::cmp::PartialEq for LiteralValue {
 }
 
 impl std::ops::Add for &LiteralValue {
-  type Output = Result<LiteralValue, String>;
+  type Output = Result<LiteralValue, OperandError>;
 
   fn add(self, rhs: &LiteralValue) -> Self::Output {
     match (self, rhs) {
@@ -226,57 +407,69 @@ impl std::ops::Add for &LiteralValue {
         let new = a.to_string() + b.as_ref();
         Ok(new.as_str().into())
       }
-      _ => Err(format!("Operands must be numbers.")),
+      _ => Err(OperandError::ExpectNumbers),
     }
   }
 }
 
 impl std::ops::Sub for &LiteralValue {
-  type Output = Result<LiteralValue, String>;
+  type Output = Result<LiteralValue, OperandError>;
 
   fn sub(self, rhs: &LiteralValue) -> Self::Output {
     match (self, rhs) {
       (LiteralValue::Number(a), LiteralValue::Number(b)) => Ok(LiteralValue::Number(a - b)),
-      _ => Err(format!("Operands must be numbers.")),
+      _ => Err(OperandError::ExpectNumbers),
     }
   }
 }
 
 impl std::ops::Mul for &LiteralValue {
-  type Output = Result<LiteralValue, String>;
+  type Output = Result<LiteralValue, OperandError>;
 
   fn mul(self, rhs: Self) -> Self::Output {
     match (self, rhs) {
       (LiteralValue::Number(a), LiteralValue::Number(b)) => Ok(LiteralValue::Number(a * b)),
-      _ => Err(format!("Operands must be numbers.")),
+      _ => Err(OperandError::ExpectNumbers),
     }
   }
 }
 
 impl std::ops::Div for &LiteralValue {
-  type Output = Result<LiteralValue, String>;
+  type Output = Result<LiteralValue, OperandError>;
 
   fn div(self, rhs: Self) -> Self::Output {
     match (self, rhs) {
       (LiteralValue::Number(a), LiteralValue::Number(b)) => Ok(LiteralValue::Number(a / b)),
-      _ => Err(format!("Operands must be numbers.")),
+      _ => Err(OperandError::ExpectNumbers),
+    }
+  }
+}
+
+/// The remainder has the sign of the dividend, e.g. `-7 % 3` is `-1`.
+impl std::ops::Rem for &LiteralValue {
+  type Output = Result<LiteralValue, OperandError>;
+
+  fn rem(self, rhs: Self) -> Self::Output {
+    match (self, rhs) {
+      (LiteralValue::Number(a), LiteralValue::Number(b)) => Ok(LiteralValue::Number(a % b)),
+      _ => Err(OperandError::ExpectNumbers),
     }
   }
 }
 
 impl std::ops::Neg for &LiteralValue {
-  type Output = Result<LiteralValue, String>;
+  type Output = Result<LiteralValue, OperandError>;
 
   fn neg(self) -> Self::Output {
     match self {
       LiteralValue::Number(n) => Ok(LiteralValue::Number(-n)),
-      _ => Err(format!("Operand must be a number.")),
+      _ => Err(OperandError::ExpectNumber),
     }
   }
 }
 
 impl std::ops::Not for &LiteralValue {
-  type Output = Result<LiteralValue, String>;
+  type Output = Result<LiteralValue, OperandError>;
 
   fn not(self) -> Self::Output {
     let res = self.is_true();
@@ -3,6 +3,7 @@ use crate::{
   visit::{Visitor, VisitorAcceptor},
   STR,
 };
+use ember_lox_tokenizer::Span;
 
 pub type VariableField = (STR, Option<Expr>);
 pub type FunctionFiled = (STR, Vec<STR>, Vec<Stmt>);
@@ -11,42 +12,88 @@ pub type FunctionFiled = (STR, Vec<STR>, Vec<Stmt>);
 pub enum Stmt {
   Block {
     stmts: Vec<Stmt>,
+    span: Span,
+  },
+  Break {
+    span: Span,
   },
   Class {
     name: STR,
     superclass: Option<VariableField>,
     methods: Vec<FunctionFiled>,
+    span: Span,
+  },
+  Continue {
+    span: Span,
   },
   Expression {
     expr: Expr,
+    span: Span,
+  },
+  /// Kept apart from [Stmt::While], so that `continue` still runs the `increment`.
+  ///
+  /// The `initializer` is scoped to the loop, and a missing `cond` is a `true` at `for`.
+  For {
+    initializer: Option<Box<Stmt>>,
+    cond: Expr,
+    increment: Option<Expr>,
+    body: Box<Stmt>,
+    span: Span,
   },
   Function {
     name: STR,
     params: Vec<STR>,
     body: Vec<Stmt>,
+    span: Span,
   },
   If {
     cond: Expr,
     then_branch: Box<Stmt>,
     else_branch: Option<Box<Stmt>>,
+    span: Span,
   },
   Print {
     expr: Expr,
+    span: Span,
   },
   Return {
     keyword: PosedLiteral,
     value: Option<Expr>,
+    span: Span,
   },
   Variable {
     name: STR,
     initializer: Option<Expr>,
+    span: Span,
   },
   While {
     cond: Expr,
     body: Box<Stmt>,
+    span: Span,
   },
 }
 
+impl Stmt {
+  /// The span of the whole statement.
+  pub fn span(&self) -> Span {
+    use Stmt::*;
+    match self {
+      Block { span, .. }
+      | Break { span }
+      | Class { span, .. }
+      | Continue { span }
+      | Expression { span, .. }
+      | For { span, .. }
+      | Function { span, .. }
+      | If { span, .. }
+      | Print { span, .. }
+      | Return { span, .. }
+      | Variable { span, .. }
+      | While { span, .. } => *span,
+    }
+  }
+}
+
 impl VisitorAcceptor for Stmt {
   fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Output {
     visitor.visit_stmt(self)
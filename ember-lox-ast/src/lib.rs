@@ -11,6 +11,7 @@ pub mod visit;
 use crate::visit::{Visitor, VisitorAcceptor};
 use ast::expr::Expr;
 use ast::stmt::Stmt;
+use ember_lox_tokenizer::Span;
 use std::sync::Arc;
 
 #[cfg(target_os = "windows")]
@@ -18,18 +19,19 @@ const NEWLINE_SEQ: &str = "\r\n";
 #[cfg(not(target_os = "windows"))]
 const NEWLINE_SEQ: &str = "\n";
 
+/// A name (e.g. identifier, keyword) with its [Span].
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct STR(pub Arc<str>, pub usize);
+pub struct STR(pub Arc<str>, pub Span);
 
-impl From<(Arc<str>, usize)> for STR {
-  fn from((s, line): (Arc<str>, usize)) -> Self {
-    Self(s, line)
+impl From<(Arc<str>, Span)> for STR {
+  fn from((s, span): (Arc<str>, Span)) -> Self {
+    Self(s, span)
   }
 }
 
-impl From<(&str, usize)> for STR {
-  fn from((s, line): (&str, usize)) -> Self {
-    Self(s.into(), line)
+impl From<(&str, Span)> for STR {
+  fn from((s, span): (&str, Span)) -> Self {
+    Self(s.into(), span)
   }
 }
 
@@ -68,8 +70,9 @@ impl AstPrinter {
       .map(|s| s.0.as_ref())
       .collect::<Vec<_>>()
       .join(", ");
+    // An anonymous one, i.e. a lambda.
     let starting = if name.0.is_empty() {
-      "(function ".to_string()
+      format!("(function ({}) ", params)
     } else {
       format!("(function {}({}) ", name.0, params)
     };
@@ -99,15 +102,17 @@ impl Visitor for AstPrinter {
     use Stmt::*;
 
     match stmt {
-      Block { stmts } => {
+      Block { stmts, .. } => {
         let starting = "(block ";
         let stmts = stmts.iter().map(|s| s.accept(self)).collect::<Vec<_>>();
         self.stringify_multi_lines(starting, &stmts, ")")
       }
+      Break { .. } => "(break)".to_string(),
       Class {
         name,
         superclass, // similar with Stmt::Variable
         methods,    // similar with Stmt::Function
+        ..
       } => {
         let superclass = superclass
           .as_ref()
@@ -126,12 +131,15 @@ impl Visitor for AstPrinter {
 
         self.stringify_multi_lines(&starting, &methods, ")")
       }
-      Expression { expr } => expr.accept(self),
-      Function { name, params, body } => self.stringify_function(name, params, body),
+      Expression { expr, .. } => expr.accept(self),
+      Function {
+        name, params, body, ..
+      } => self.stringify_function(name, params, body),
       If {
         cond,
         then_branch,
         else_branch,
+        ..
       } => {
         // let if_then = format!(
         //   "(if {} then {}",
@@ -156,8 +164,8 @@ impl Visitor for AstPrinter {
           format!("{}", if_then)
         }
       }
-      Print { expr } => format!("(print {})", expr.accept(self)),
-      Return { keyword: _, value } => format!(
+      Print { expr, .. } => format!("(print {})", expr.accept(self)),
+      Return { value, .. } => format!(
         "(return{})",
         if value.is_none() {
           "".to_string()
@@ -165,11 +173,28 @@ impl Visitor for AstPrinter {
           format!(" {}", value.as_ref().unwrap().accept(self))
         }
       ),
-      Variable { name, initializer } => {
+      Variable {
+        name, initializer, ..
+      } => {
         let str = self.stringify_variable(name, initializer);
         format!("{}", str)
       }
-      While { cond, body } => {
+      Continue { .. } => "(continue)".to_string(),
+      For {
+        initializer,
+        cond,
+        increment,
+        body,
+        ..
+      } => {
+        // Missing clauses are shown as `_`.
+        let initializer = initializer.as_ref().map_or("_".into(), |i| i.accept(self));
+        let increment = increment.as_ref().map_or("_".into(), |i| i.accept(self));
+        let starting = format!("(for {} {} {} ", initializer, cond.accept(self), increment);
+        let body = body.accept(self);
+        self.stringify_multi_lines(&starting, &[body], ")")
+      }
+      While { cond, body, .. } => {
         let starting = format!("(while {} ", cond.accept(self));
         let body = body.accept(self);
         self.stringify_multi_lines(&starting, &[body], ")")
@@ -181,11 +206,13 @@ impl Visitor for AstPrinter {
     use Expr::*;
 
     match expr {
-      Assign { name, val } => format!("(assign {} {})", name.0, val.accept(self)),
-      Binary { left, op, right } => {
+      Assign { name, val, .. } => format!("(assign {} {})", name.0, val.accept(self)),
+      Binary {
+        left, op, right, ..
+      } => {
         format!("({} {} {})", op.0, left.accept(self), right.accept(self))
       }
-      Call { callee, args } => format!(
+      Call { callee, args, .. } => format!(
         "(call {} with [{}])",
         callee.accept(self),
         args
@@ -194,13 +221,41 @@ impl Visitor for AstPrinter {
           .collect::<Vec<_>>()
           .join(", ")
       ),
-      Get { obj, name } => format!("(get {}.{})", obj.accept(self), name.0),
-      Grouping { expr } => format!("(group {})", expr.accept(self)),
-      Literal { val } => format!("{:?}", val.0),
-      Logical { left, op, right } => {
+      Get { obj, name, .. } => format!("(get {}.{})", obj.accept(self), name.0),
+      Grouping { expr, .. } => format!("(group {})", expr.accept(self)),
+      Interpolation { parts, .. } => format!(
+        "(interpolate [{}])",
+        parts
+          .iter()
+          .map(|p| p.accept(self))
+          .collect::<Vec<_>>()
+          .join(", ")
+      ),
+      Index { obj, index, .. } => format!("(get {}[{}])", obj.accept(self), index.accept(self)),
+      Lambda { params, body, span } => self.stringify_function(&("", *span).into(), params, body),
+      List { elements, .. } => format!(
+        "(list [{}])",
+        elements
+          .iter()
+          .map(|e| e.accept(self))
+          .collect::<Vec<_>>()
+          .join(", ")
+      ),
+      Literal { val, .. } => format!("{:?}", val.0),
+      Map { entries, .. } => format!(
+        "(map [{}])",
+        entries
+          .iter()
+          .map(|(k, v)| format!("{}: {}", k.accept(self), v.accept(self)))
+          .collect::<Vec<_>>()
+          .join(", ")
+      ),
+      Logical {
+        left, op, right, ..
+      } => {
         format!("({} {} {})", op.0, left.accept(self), right.accept(self))
       }
-      Set { obj, name, val } => {
+      Set { obj, name, val, .. } => {
         format!(
           "(set {}.{} <- {})",
           obj.accept(self),
@@ -208,10 +263,46 @@ impl Visitor for AstPrinter {
           val.accept(self)
         )
       }
-      Super { keyword: _, method } => format!("(super {})", method.0),
-      This { keyword: _ } => format!("(this)"),
-      Unary { op, right } => format!("({} {})", op.0, right.accept(self)),
-      Var { name } => format!("(var {})", name.0),
+      SetIndex {
+        obj, index, val, ..
+      } => format!(
+        "(set {}[{}] <- {})",
+        obj.accept(self),
+        index.accept(self),
+        val.accept(self)
+      ),
+      Super { method, .. } => format!("(super {})", method.0),
+      This { .. } => format!("(this)"),
+      Ternary {
+        cond,
+        then_branch,
+        else_branch,
+        ..
+      } => format!(
+        "(?: {} {} {})",
+        cond.accept(self),
+        then_branch.accept(self),
+        else_branch.accept(self)
+      ),
+      Unary { op, right, .. } => format!("({} {})", op.0, right.accept(self)),
+      // e.g. `(+= (var a) 2.0)`, `(++ (var a))` and `(post-- (var a))`
+      Update {
+        target,
+        op,
+        val,
+        is_postfix,
+        ..
+      } => match val {
+        Some(val) => format!("({}= {} {})", op.0, target.accept(self), val.accept(self)),
+        None => format!(
+          "({}{}{} {})",
+          if *is_postfix { "post" } else { "" },
+          op.0,
+          op.0,
+          target.accept(self)
+        ),
+      },
+      Var { name, .. } => format!("(var {})", name.0),
     }
   }
 }
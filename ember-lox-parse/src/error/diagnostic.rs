@@ -0,0 +1,96 @@
+use ember_lox_tokenizer::Span;
+
+/// How serious a [Diagnostic] is, which is shown as its label, e.g. `error:`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+  Error,
+  Warning,
+}
+
+/// Which stage a [Diagnostic] comes from, it decides the compatible format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stage {
+  /// `[line N] Error: <message>`
+  ///
+  /// `line` is where the tokenizer stops, e.g. the last line of an unterminated string.
+  Lexical { line: usize },
+  /// `[line N] Error at '<lexeme>': <message>` (or `at end`, if `lexeme` is `None`)
+  ///
+  /// Used by both the parser and the resolver.
+  Static { lexeme: Option<String> },
+  /// `<message>` followed by `[line N]`
+  Runtime,
+}
+
+/// A secondary span with its own message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+  pub span: Span,
+  pub message: String,
+}
+
+/// A problem found in the source code, which is rendered by a [Renderer](super::Renderer).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  pub stage: Stage,
+  pub message: String,
+  /// Where the problem is.
+  pub span: Span,
+  pub labels: Vec<Label>,
+  pub notes: Vec<String>,
+  /// Active calls of a runtime error, innermost first, e.g. `[line 3] in foo()`.
+  pub trace: Vec<String>,
+}
+
+impl Diagnostic {
+  pub fn new(severity: Severity, stage: Stage, message: impl Into<String>, span: Span) -> Self {
+    Self {
+      severity,
+      stage,
+      message: message.into(),
+      span,
+      labels: vec![],
+      notes: vec![],
+      trace: vec![],
+    }
+  }
+
+  pub fn lexical(message: impl Into<String>, span: Span, line: usize) -> Self {
+    Self::new(Severity::Error, Stage::Lexical { line }, message, span)
+  }
+
+  /// `lexeme` is `None` if the problem is at the end of input.
+  pub fn at(lexeme: Option<&str>, message: impl Into<String>, span: Span) -> Self {
+    let stage = Stage::Static {
+      lexeme: lexeme.map(str::to_string),
+    };
+    Self::new(Severity::Error, stage, message, span)
+  }
+
+  pub fn runtime(message: impl Into<String>, span: Span) -> Self {
+    Self::new(Severity::Error, Stage::Runtime, message, span)
+  }
+
+  pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+    self.labels.push(Label {
+      span,
+      message: message.into(),
+    });
+    self
+  }
+
+  pub fn with_note(mut self, note: impl Into<String>) -> Self {
+    self.notes.push(note.into());
+    self
+  }
+
+  pub fn with_trace(mut self, trace: Vec<String>) -> Self {
+    self.trace = trace;
+    self
+  }
+
+  pub fn is_error(&self) -> bool {
+    self.severity == Severity::Error
+  }
+}
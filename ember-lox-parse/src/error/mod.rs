@@ -1,21 +1,11 @@
-//! Reporting functions for errors.
+//! Errors, diagnostics, and how they are rendered.
+//!
+//! Errors are collected by each stage, converted into [Diagnostic]s, and only printed by the caller.
 
-use crate::Token;
+pub mod diagnostic;
+pub mod parse_error;
+pub mod render;
 
-pub fn report(line: usize, msg: &str) {
-  eprintln!("[line {}] Error: {}", line, msg)
-}
-
-pub fn report_detail(line: usize, lexeme: Option<&str>, msg: &str) {
-  match lexeme {
-    Some(s) => eprintln!("[line {}] Error at '{}': {}", line, s, msg),
-    None => eprintln!("[line {}] Error at end: {}", line, msg),
-  }
-}
-
-pub fn report_token(line: usize, token: Option<&Token>, msg: &str) {
-  match token {
-    Some(t) => eprintln!("[line {}] Error at '{}': {}", line, t.val, msg),
-    None => eprintln!("[line {}] Error at end: {}", line, msg),
-  }
-}
+pub use diagnostic::{Diagnostic, Label, Severity, Stage};
+pub use parse_error::ParseError;
+pub use render::{RenderMode, Renderer};
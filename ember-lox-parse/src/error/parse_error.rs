@@ -0,0 +1,134 @@
+use super::diagnostic::Diagnostic;
+use crate::parser::MAX_ARGS;
+use ember_lox_tokenizer::{Span, TokenKind, TokenizationError};
+use std::fmt;
+
+/// An error found by [Parser](crate::parser::Parser), including the ones from tokenization.
+///
+/// `found` is the lexeme of the offending token, `None` means the end of input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+  /// A [TokenKind::TokErr] token.
+  Lexical {
+    error: TokenizationError,
+    lexeme: String,
+    span: Span,
+  },
+  /// The `expected` token is missing, `message` tells where it should be.
+  ExpectedToken {
+    expected: TokenKind,
+    message: String,
+    found: Option<String>,
+    span: Span,
+  },
+  ExpectedExpression {
+    found: Option<String>,
+    span: Span,
+  },
+  /// e.g. `1 = 2`, located at the `=`.
+  InvalidAssignmentTarget {
+    found: String,
+    span: Span,
+  },
+  /// More than [MAX_ARGS] arguments, located at the first extra one.
+  TooManyArguments {
+    found: Option<String>,
+    span: Span,
+  },
+  /// More than [MAX_ARGS] parameters, located at the first extra one.
+  TooManyParameters {
+    found: Option<String>,
+    span: Span,
+  },
+  /// `break` or `continue` (the `keyword`) outside of any loop.
+  OutsideLoop {
+    keyword: &'static str,
+    span: Span,
+  },
+}
+
+impl ParseError {
+  pub fn span(&self) -> Span {
+    match self {
+      ParseError::Lexical { span, .. }
+      | ParseError::ExpectedToken { span, .. }
+      | ParseError::ExpectedExpression { span, .. }
+      | ParseError::InvalidAssignmentTarget { span, .. }
+      | ParseError::TooManyArguments { span, .. }
+      | ParseError::TooManyParameters { span, .. }
+      | ParseError::OutsideLoop { span, .. } => *span,
+    }
+  }
+
+  /// Lexeme of the offending token, `None` for [ParseError::Lexical] or the end of input.
+  pub fn found(&self) -> Option<&str> {
+    match self {
+      ParseError::Lexical { .. } => None,
+      ParseError::ExpectedToken { found, .. }
+      | ParseError::ExpectedExpression { found, .. }
+      | ParseError::TooManyArguments { found, .. }
+      | ParseError::TooManyParameters { found, .. } => found.as_deref(),
+      ParseError::InvalidAssignmentTarget { found, .. } => Some(found),
+      ParseError::OutsideLoop { keyword, .. } => Some(keyword),
+    }
+  }
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    use TokenizationError::*;
+
+    match self {
+      ParseError::Lexical { error, lexeme, .. } => match error {
+        InvalidIdent { .. } => write!(f, "Invalid identifier: {}", lexeme),
+        UnexpectedCharacter { ch, .. } => write!(f, "Unexpected character: {}", ch),
+        UnterminatedString { .. } => write!(f, "Unterminated string."),
+        UnterminatedBlockComment { .. } => write!(f, "Unterminated block comment."),
+        UnknownPrefix { .. } => write!(f, "Unknown prefix: {}", lexeme),
+        // The lexeme is the prefix only, as written (e.g. `0X`).
+        EmptyDigits { base, .. } => write!(f, "Expect {} digits after '{}'.", base.name(), lexeme),
+        InvalidDigit { ch, base, .. } => {
+          write!(
+            f,
+            "Invalid digit '{}' in {} literal {}.",
+            ch,
+            base.name(),
+            lexeme
+          )
+        }
+        EmptyExponent { .. } => write!(f, "Expect digits in the exponent of {}.", lexeme),
+        TrailingSeparator { base, .. } => {
+          write!(f, "Trailing '_' in {} literal {}.", base.name(), lexeme)
+        }
+        InvalidEscape { ch, .. } => write!(f, "Invalid escape sequence: \\{}", ch),
+      },
+      ParseError::ExpectedToken { message, .. } => write!(f, "{}", message),
+      ParseError::ExpectedExpression { .. } => write!(f, "Expect expression."),
+      ParseError::InvalidAssignmentTarget { .. } => write!(f, "Invalid assignment target."),
+      ParseError::TooManyArguments { .. } => {
+        write!(f, "Cannot have more than {} arguments.", MAX_ARGS)
+      }
+      ParseError::TooManyParameters { .. } => {
+        write!(f, "Cannot have more than {} parameters.", MAX_ARGS)
+      }
+      ParseError::OutsideLoop { keyword, .. } => {
+        write!(f, "Can't use '{}' outside of a loop.", keyword)
+      }
+    }
+  }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<&ParseError> for Diagnostic {
+  fn from(error: &ParseError) -> Self {
+    match error {
+      ParseError::Lexical {
+        error: lexical,
+        span,
+        ..
+      } => Diagnostic::lexical(error.to_string(), *span, lexical.line()),
+      _ => Diagnostic::at(error.found(), error.to_string(), error.span()),
+    }
+  }
+}
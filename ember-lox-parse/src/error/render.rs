@@ -0,0 +1,177 @@
+use super::diagnostic::{Diagnostic, Severity, Stage};
+use ember_lox_tokenizer::Span;
+use std::{fmt::Write, str::FromStr};
+
+/// Frames shown at each end of a long stack trace (e.g. of a stack overflow).
+const TRACE_EDGE_LEN: usize = 10;
+
+/// How a [Diagnostic] is rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderMode {
+  /// Message with the source snippet, underlined by carets.
+  #[default]
+  Rich,
+  /// The `[line N] Error...` format without stack traces, expected by the `codecrafters` tests.
+  Compat,
+}
+
+impl FromStr for RenderMode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "rich" => Ok(RenderMode::Rich),
+      "compat" => Ok(RenderMode::Compat),
+      _ => Err(format!("Unknown diagnostics mode: {}", s)),
+    }
+  }
+}
+
+/// Renders [Diagnostic]s against the source they come from.
+pub struct Renderer<'src> {
+  src: &'src str,
+  /// Name of the source, e.g. the file name.
+  name: &'src str,
+  mode: RenderMode,
+}
+
+impl<'src> Renderer<'src> {
+  pub fn new(src: &'src str, name: &'src str, mode: RenderMode) -> Self {
+    Self { src, name, mode }
+  }
+
+  /// Prints `diagnostic` to `stderr`.
+  pub fn emit(&self, diagnostic: &Diagnostic) {
+    eprintln!("{}", self.render(diagnostic));
+  }
+
+  pub fn emit_all<'d>(&self, diagnostics: impl IntoIterator<Item = &'d Diagnostic>) {
+    diagnostics.into_iter().for_each(|d| self.emit(d));
+  }
+
+  /// Renders `diagnostic`, without the trailing new line.
+  pub fn render(&self, diagnostic: &Diagnostic) -> String {
+    match self.mode {
+      RenderMode::Rich => self.render_rich(diagnostic),
+      RenderMode::Compat => render_compat(diagnostic),
+    }
+  }
+
+  /// ```text
+  /// error: Operands must be numbers.
+  ///  --> main.lox:1:9
+  ///   |
+  /// 1 | print 1 + "a";
+  ///   |         ^
+  ///   = note: ...
+  ///   = stack trace:
+  ///     [line 1] in foo()
+  ///     [line 3] in script
+  /// ```
+  fn render_rich(&self, diagnostic: &Diagnostic) -> String {
+    let Diagnostic {
+      severity,
+      message,
+      span,
+      labels,
+      notes,
+      trace,
+      ..
+    } = diagnostic;
+
+    let severity = match severity {
+      Severity::Error => "error",
+      Severity::Warning => "warning",
+    };
+    let mut out = format!("{}: {}", severity, message);
+
+    // The primary span and labels, ordered by where they are.
+    let mut marks = vec![(*span, '^', "")];
+    marks.extend(labels.iter().map(|l| (l.span, '-', l.message.as_str())));
+    marks.sort_by_key(|(span, ..)| (span.line, span.col));
+
+    let max_line = marks
+      .iter()
+      .map(|(span, ..)| span.line)
+      .max()
+      .unwrap_or_default();
+    let pad = " ".repeat(max_line.to_string().len());
+
+    if span.line > 0 {
+      write!(out, "\n{}--> {}:{}:{}", pad, self.name, span.line, span.col).unwrap();
+    }
+    write!(out, "\n{} |", pad).unwrap();
+
+    let mut last_line = None;
+    for (span, mark, label) in marks {
+      let Some(text) = self.line_text(span.line) else {
+        continue;
+      };
+      if last_line != Some(span.line) {
+        write!(out, "\n{:>width$} | {}", span.line, text, width = pad.len()).unwrap();
+        last_line = Some(span.line);
+      }
+      let indent = " ".repeat(span.col.saturating_sub(1));
+      let underline = mark.to_string().repeat(self.underline_len(span));
+      let underline = format!("{}{} {}", indent, underline, label);
+      write!(out, "\n{} | {}", pad, underline.trim_end()).unwrap();
+    }
+
+    for note in notes {
+      write!(out, "\n{} = note: {}", pad, note).unwrap();
+    }
+    // Not interesting, if the error is raised right in the script.
+    if trace.len() > 1 {
+      write!(out, "\n{} = stack trace:", pad).unwrap();
+      for (i, frame) in trace.iter().enumerate() {
+        let omitted = trace.len().saturating_sub(2 * TRACE_EDGE_LEN);
+        if omitted == 0 || i < TRACE_EDGE_LEN || i >= TRACE_EDGE_LEN + omitted {
+          write!(out, "\n{}     {}", pad, frame).unwrap();
+        } else if i == TRACE_EDGE_LEN {
+          write!(out, "\n{}     ... ({} frames omitted)", pad, omitted).unwrap();
+        }
+      }
+    }
+    out
+  }
+
+  fn line_text(&self, line: usize) -> Option<&'src str> {
+    self.src.lines().nth(line.checked_sub(1)?)
+  }
+
+  /// Number of characters to underline, only the first line of `span` is taken into account.
+  fn underline_len(&self, span: Span) -> usize {
+    let text = self.src.get(span.start..span.end).unwrap_or_default();
+    text.chars().take_while(|&c| c != '\n').count().max(1)
+  }
+}
+
+fn render_compat(diagnostic: &Diagnostic) -> String {
+  let Diagnostic {
+    severity,
+    stage,
+    message,
+    span,
+    ..
+  } = diagnostic;
+
+  let severity = match severity {
+    Severity::Error => "Error",
+    Severity::Warning => "Warning",
+  };
+  match stage {
+    Stage::Lexical { line } => format!("[line {}] {}: {}", line, severity, message),
+    Stage::Static {
+      lexeme: Some(lexeme),
+    } => {
+      format!(
+        "[line {}] {} at '{}': {}",
+        span.line, severity, lexeme, message
+      )
+    }
+    Stage::Static { lexeme: None } => {
+      format!("[line {}] {} at end: {}", span.line, severity, message)
+    }
+    Stage::Runtime => format!("{}\n[line {}]", message, span.line),
+  }
+}
@@ -5,6 +5,7 @@
 //! - [rustc_parse](https://github.com/rust-lang/rust/tree/master/compiler/rustc_parse)
 
 use ember_lox_tokenizer::prelude::*;
+use error::ParseError;
 use macros::gen_reserved_tok_methods;
 use parser::Parser;
 use std::{collections::HashSet, sync::LazyLock};
@@ -13,7 +14,10 @@ pub mod error;
 pub mod parser;
 
 pub mod prelude {
-  pub use super::{new_parser_from_src_str, tag_to_named_tokens, Token, RESERVED_WORDS};
+  pub use super::{
+    new_parser_at, new_parser_from_src_str, parse_number, parse_string, tag_to_named_tokens, Token,
+    RESERVED_WORDS,
+  };
   pub use ember_lox_tokenizer::prelude::*;
 }
 
@@ -22,12 +26,22 @@ use TokenKind::*;
 ///
 /// Will filter out listed [TokenKind]:
 ///
-/// [Eof], [Whitespace], [NewLine], [LineComment]
+/// [Eof], [Whitespace], [NewLine], [LineComment], [BlockComment]
 pub fn new_parser_from_src_str<'src>(str: &'src str) -> Parser<'src> {
-  let tag_tokens = tokenize(str);
+  new_parser_at(str, 0, 1)
+}
+
+/// Like [new_parser_from_src_str], but `str` starts at byte offset `pos` and `line` of
+/// the whole source (e.g. the input so far in `REPL`).
+pub fn new_parser_at(str: &str, pos: usize, line: usize) -> Parser<'_> {
+  let tag_tokens = tokenize_at(str, pos, line);
   let tokens = tag_to_named_tokens(str, tag_tokens);
-  let meaningful_tokens =
-    tokens.filter(|t| !matches!(t.tag.kind, Eof | Whitespace | NewLine | LineComment));
+  let meaningful_tokens = tokens.filter(|t| {
+    !matches!(
+      t.tag.kind,
+      Eof | Whitespace | NewLine | LineComment | BlockComment
+    )
+  });
   Parser::new(meaningful_tokens)
 }
 
@@ -47,6 +61,10 @@ impl<'src> Token<'src> {
         kind: TokenKind::Eof,
         len: 0,
         line,
+        span: Span {
+          line,
+          ..Default::default()
+        },
       },
       val: "",
     }
@@ -56,8 +74,8 @@ impl<'src> Token<'src> {
 /// Special identifies for the token.
 pub static RESERVED_WORDS: LazyLock<HashSet<&str>> = LazyLock::new(|| {
   [
-    "and", "class", "else", "false", "for", "fun", "if", "nil", "or", "print", "return", "super",
-    "this", "true", "var", "while",
+    "and", "break", "class", "continue", "else", "false", "for", "fun", "if", "nil", "or", "print",
+    "return", "super", "this", "true", "var", "while",
   ]
   .iter()
   .copied()
@@ -65,12 +83,26 @@ pub static RESERVED_WORDS: LazyLock<HashSet<&str>> = LazyLock::new(|| {
 });
 
 gen_reserved_tok_methods!([
-  "and", "class", "else", "false", "for", "fun", "if", "nil", "or", "print", "return", "super",
-  "this", "true", "var", "while"
+  "and", "break", "class", "continue", "else", "false", "for", "fun", "if", "nil", "or", "print",
+  "return", "super", "this", "true", "var", "while"
 ]);
 
 use LiteralKind::*;
-use TokenizationError::*;
+
+impl Token<'_> {
+  /// Converts a [TokErr] token into a [ParseError].
+  pub fn lexical_error(&self) -> Option<ParseError> {
+    match self.tag.kind {
+      TokErr(error) => ParseError::Lexical {
+        error,
+        lexeme: self.val.to_string(),
+        span: self.tag.span,
+      }
+      .into(),
+      _ => None,
+    }
+  }
+}
 
 /// Transmute [TagToken] to [Token].
 pub fn tag_to_named_tokens<'src>(
@@ -94,14 +126,14 @@ impl Token<'_> {
   pub fn dbg(&self) -> String {
     let val = self.val;
     match self.tag.kind {
-      LineComment | NewLine | Whitespace => String::new(),
+      LineComment | BlockComment | NewLine | Whitespace => String::new(),
       Identifier => match RESERVED_WORDS.get(val) {
         Some(_) => format!("{} {} null", val.to_uppercase(), val),
         None => format!("IDENTIFIER {} null", val),
       },
       Literal { kind } => match kind {
         Number => {
-          let mut displayed = val.parse::<f64>().unwrap_or_default().to_string();
+          let mut displayed = parse_number(val).to_string();
           // e.g. (123 -> ) 123 => 123.0
           if !displayed.contains(".") {
             displayed += ".0";
@@ -109,21 +141,78 @@ impl Token<'_> {
           format!("NUMBER {} {}", val, displayed)
         }
         Str => {
-          debug_assert!(val.starts_with('"') && val.ends_with('"'));
-          let len = val.len();
           // According to the test case, lexeme should remove the quotes.
-          format!("STRING {} {}", val, &val[1..len - 1])
-        }
-      },
-      TokErr(tokenization_error) => match tokenization_error {
-        InvalidIdent { line } => format!("[line {}] Error: Invalid identifier: {}", line, val),
-        UnexpectedCharacter { ch, line } => {
-          format!("[line {}] Error: Unexpected character: {}", line, ch)
+          format!("STRING {} {}", val, parse_string(val))
         }
-        UnterminatedString { line } => format!("[line {}] Error: Unterminated string.", line),
-        UnknownPrefix { line } => format!("[line {}] Error: Unknown prefix: {}", line, val),
+        StrHead => format!("STRING_HEAD {} {}", val, parse_string(val)),
+        StrMiddle => format!("STRING_MIDDLE {} {}", val, parse_string(val)),
+        StrTail => format!("STRING_TAIL {} {}", val, parse_string(val)),
       },
+      TokErr(e) => format!(
+        "[line {}] Error: {}",
+        e.line(),
+        self.lexical_error().unwrap()
+      ),
       _ => self.tag.dbg_pure_tag(),
     }
   }
 }
+
+/// The value of a [Number] literal, e.g. `1_000`, `2.5e-3` or `0xff`.
+///
+/// `lexeme` should be a valid one, which is checked by the tokenizer.
+pub fn parse_number(lexeme: &str) -> f64 {
+  // Prefixes and exponents could be in any case.
+  let digits = lexeme.replace('_', "").to_ascii_lowercase();
+  let base = [Base::Binary, Base::Octal, Base::Hexadecimal]
+    .into_iter()
+    .find(|base| digits.starts_with(base.prefix()));
+  match base {
+    // Too many digits for an integer type would still be (roughly) fine.
+    Some(base) => digits[2..].chars().fold(0.0, |acc, c| {
+      acc * (base as u8 as f64) + c.to_digit(base as u32).unwrap_or_default() as f64
+    }),
+    None => digits.parse().unwrap_or_default(),
+  }
+}
+
+/// The value of a [Str] literal (or a segment of an interpolated one),
+/// i.e. without the delimiters (`"`, `}` and `${`) and with escapes decoded.
+///
+/// `lexeme` should be a valid one, which is checked by the tokenizer.
+pub fn parse_string(lexeme: &str) -> String {
+  debug_assert!(lexeme.starts_with(['"', '}']));
+  let raw = &lexeme[1..];
+  let raw = raw
+    .strip_suffix("${")
+    .or_else(|| raw.strip_suffix('"'))
+    .unwrap_or(raw);
+  let mut value = String::with_capacity(raw.len());
+  let mut chars = raw.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      value.push(c);
+      continue;
+    }
+    match chars.next() {
+      Some('n') => value.push('\n'),
+      Some('t') => value.push('\t'),
+      Some('r') => value.push('\r'),
+      Some('0') => value.push('\0'),
+      // `\u{XXXX}`
+      Some('u') => {
+        let code = chars
+          .by_ref()
+          .skip(1)
+          .take_while(|&c| c != '}')
+          .collect::<String>();
+        let decoded = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32);
+        value.push(decoded.unwrap_or(char::REPLACEMENT_CHARACTER));
+      }
+      // `\\`, `\"` and `\$`
+      Some(c) => value.push(c),
+      None => {}
+    }
+  }
+  value
+}
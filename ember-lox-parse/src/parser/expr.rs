@@ -1,7 +1,5 @@
 use super::*;
 
-const MAX_ARGS: usize = u8::MAX as usize;
-
 impl<'src> Parser<'src> {
   /// ```
   /// expression → assignment ;
@@ -11,32 +9,122 @@ impl<'src> Parser<'src> {
   }
 
   /// ```
-  /// assignment → IDENTIFIER "=" assignment
-  ///            |  logic_or ;
+  /// assignment → ( call "." )? IDENTIFIER ( "=" | "+=" | "-=" | "*=" | "/=" ) assignment
+  ///            |  call "[" expression "]" ( "=" | "+=" | "-=" | "*=" | "/=" ) assignment
+  ///            |  ternary ;
   /// ```
   fn assignment(&mut self) -> Option<Expr> {
-    let expr = self.or()?;
+    let expr = self.ternary()?;
 
     if self.match_kind(TokenKind::Eq) {
       let equal_token = self.prev().unwrap().to_owned();
-      let line = equal_token.tag.line;
       let val = self.assignment()?;
-
-      if let Expr::Var { name } = expr {
-        return Expr::Assign {
-          name,
-          val: val.into(),
+      let span = expr.span().to(val.span());
+
+      match expr {
+        Expr::Var { name, depth, .. } => {
+          return Expr::Assign {
+            name,
+            val: val.into(),
+            depth,
+            span,
+          }
+          .into();
         }
-        .into();
+        // `obj.field = val`
+        Expr::Get { obj, name, .. } => {
+          return Expr::Set {
+            obj,
+            name,
+            val: val.into(),
+            span,
+          }
+          .into();
+        }
+        // `obj[index] = val`
+        Expr::Index { obj, index, .. } => {
+          return Expr::SetIndex {
+            obj,
+            index,
+            val: val.into(),
+            span,
+          }
+          .into();
+        }
+        _ => {}
       }
 
-      self.had_parsing_error = true;
-      report_token(line, Some(&equal_token), "Invalid assignment target.");
+      self.report(ParseError::InvalidAssignmentTarget {
+        found: equal_token.val.to_string(),
+        span: equal_token.tag.span,
+      });
+    } else if self.match_kind_in(&[
+      TokenKind::PlusEq,
+      TokenKind::MinusEq,
+      TokenKind::StarEq,
+      TokenKind::SlashEq,
+    ]) {
+      let op_token = self.prev().unwrap().to_owned();
+      let val = self.assignment()?;
+      return self.update(expr, op_token, Some(val), false);
     }
 
     Some(expr)
   }
 
+  /// Makes an [Expr::Update] of `target` by `op_token`, which must be an assignable one.
+  fn update(
+    &mut self,
+    target: Expr,
+    op_token: Token<'src>,
+    val: Option<Expr>,
+    is_postfix: bool,
+  ) -> Option<Expr> {
+    if !is_assignable(&target) {
+      self.report(ParseError::InvalidAssignmentTarget {
+        found: op_token.val.to_string(),
+        span: op_token.tag.span,
+      });
+      return Some(target);
+    }
+
+    let op_span = op_token.tag.span;
+    let span = match &val {
+      Some(val) => target.span().to(val.span()),
+      None if is_postfix => target.span().to(op_span),
+      None => op_span.to(target.span()),
+    };
+    Expr::Update {
+      target: target.into(),
+      op: (op_token.tag.kind.try_into().unwrap(), op_span).into(),
+      val: val.map(Box::new),
+      is_postfix,
+      span,
+    }
+    .into()
+  }
+
+  /// ```
+  /// ternary → logic_or ( "?" expression ":" ternary )? ;
+  /// ```
+  fn ternary(&mut self) -> Option<Expr> {
+    let cond = self.or()?;
+    if !self.match_kind(TokenKind::Question) {
+      return Some(cond);
+    }
+
+    let then_branch = self.expression()?;
+    self.consume_by_kind(TokenKind::Colon, "Expect ':' after then branch of '?'.")?;
+    let else_branch = self.ternary()?;
+    Expr::Ternary {
+      span: cond.span().to(else_branch.span()),
+      cond: cond.into(),
+      then_branch: then_branch.into(),
+      else_branch: else_branch.into(),
+    }
+    .into()
+  }
+
   /// ```
   /// logic_or → logic_and ( "or" logic_and )* ;
   /// ```
@@ -47,8 +135,9 @@ impl<'src> Parser<'src> {
       let or_op = self.prev().unwrap().to_owned();
       let right = self.and()?;
       expr = Expr::Logical {
+        span: expr.span().to(right.span()),
         left: expr.into(),
-        op: (Operator::Or, or_op.tag.line).into(),
+        op: (Operator::Or, or_op.tag.span).into(),
         right: right.into(),
       }
     }
@@ -66,8 +155,9 @@ impl<'src> Parser<'src> {
       let and_op = self.prev().unwrap().to_owned();
       let right = self.equality()?;
       expr = Expr::Logical {
+        span: expr.span().to(right.span()),
         left: expr.into(),
-        op: (Operator::And, and_op.tag.line).into(),
+        op: (Operator::And, and_op.tag.span).into(),
         right: right.into(),
       }
       .into();
@@ -85,11 +175,11 @@ impl<'src> Parser<'src> {
     while self.match_kind_in(&[TokenKind::BangEq, TokenKind::EqEq]) {
       let tag = self.prev().unwrap().tag;
       let op: Operator = tag.kind.try_into().unwrap();
-      let line = tag.line;
       let right = self.comparison()?;
       expr = Expr::Binary {
+        span: expr.span().to(right.span()),
         left: expr.into(),
-        op: (op, line).into(),
+        op: (op, tag.span).into(),
         right: right.into(),
       }
       .into();
@@ -112,11 +202,11 @@ impl<'src> Parser<'src> {
     ]) {
       let tag = self.prev().unwrap().tag;
       let op: Operator = tag.kind.try_into().unwrap();
-      let line = tag.line;
       let right = self.term()?;
       expr = Expr::Binary {
+        span: expr.span().to(right.span()),
         left: expr.into(),
-        op: (op, line).into(),
+        op: (op, tag.span).into(),
         right: right.into(),
       }
       .into();
@@ -134,11 +224,11 @@ impl<'src> Parser<'src> {
     while self.match_kind_in(&[TokenKind::Minus, TokenKind::Plus]) {
       let tag = self.prev().unwrap().tag;
       let op: Operator = tag.kind.try_into().unwrap();
-      let line = tag.line;
       let right = self.factor()?;
       expr = Expr::Binary {
+        span: expr.span().to(right.span()),
         left: expr.into(),
-        op: (op, line).into(),
+        op: (op, tag.span).into(),
         right: right.into(),
       }
       .into();
@@ -148,19 +238,19 @@ impl<'src> Parser<'src> {
   }
 
   /// ```
-  /// factor → unary ( ( "/" | "*" ) unary )* ;
+  /// factor → unary ( ( "/" | "*" | "%" ) unary )* ;
   /// ```
   fn factor(&mut self) -> Option<Expr> {
     let mut expr = self.unary()?;
 
-    while self.match_kind_in(&[TokenKind::Slash, TokenKind::Star]) {
+    while self.match_kind_in(&[TokenKind::Slash, TokenKind::Star, TokenKind::Percent]) {
       let tag = self.prev().unwrap().tag;
       let op: Operator = tag.kind.try_into().unwrap();
-      let line = tag.line;
       let right = self.unary()?;
       expr = Expr::Binary {
+        span: expr.span().to(right.span()),
         left: expr.into(),
-        op: (op, line).into(),
+        op: (op, tag.span).into(),
         right: right.into(),
       }
       .into();
@@ -170,26 +260,84 @@ impl<'src> Parser<'src> {
   }
 
   /// ```
-  /// unary → ( "!" | "-" ) unary | call ;
+  /// unary → ( "!" | "-" ) unary
+  ///       |  ( "++" | "--" ) unary
+  ///       |  power ;
   /// ```
+  ///
+  /// `--` before an operand that can't be assigned is two negations, e.g. `--1` is `-(-1)`.
   fn unary(&mut self) -> Option<Expr> {
     if self.match_kind_in(&[TokenKind::Bang, TokenKind::Minus]) {
       let tag = self.prev().unwrap().tag;
       let op: Operator = tag.kind.try_into().unwrap();
-      let line = tag.line;
       let right = self.unary()?;
       return Expr::Unary {
-        op: (op, line).into(),
+        op: (op, tag.span).into(),
+        span: tag.span.to(right.span()),
         right: right.into(),
       }
       .into();
     }
+    if self.match_kind_in(&[TokenKind::PlusPlus, TokenKind::MinusMinus]) {
+      let op_token = self.prev().unwrap().to_owned();
+      let target = self.unary()?;
+      if op_token.tag.kind == TokenKind::MinusMinus && !is_assignable(&target) {
+        let [outer, inner] = split_minus_minus(op_token.tag.span);
+        return negate(outer, negate(inner, target)).into();
+      }
+      return self.update(target, op_token, None, false);
+    }
+
+    self.power()
+  }
+
+  /// ```
+  /// power → postfix ( "**" unary )? ;
+  /// ```
+  ///
+  /// It's right-associative, and binds tighter than a unary operator on its left,
+  /// e.g. `-2 ** 2` is `-(2 ** 2)`.
+  fn power(&mut self) -> Option<Expr> {
+    let expr = self.postfix()?;
+    if !self.match_kind(TokenKind::StarStar) {
+      return Some(expr);
+    }
+
+    let tag = self.prev().unwrap().tag;
+    let right = self.unary()?;
+    Expr::Binary {
+      span: expr.span().to(right.span()),
+      left: expr.into(),
+      op: (Operator::Power, tag.span).into(),
+      right: right.into(),
+    }
+    .into()
+  }
+
+  /// ```
+  /// postfix → call ( "++" | "--" )? ;
+  /// ```
+  ///
+  /// `--` after an operand that can't be assigned is left as two `-`, e.g. `1--1` is `1 - -1`.
+  fn postfix(&mut self) -> Option<Expr> {
+    let expr = self.call()?;
+    // A `--` followed by an operand is two `-` as in plain Lox, e.g. `a--b` is `a - -b`.
+    if self.check_kind(TokenKind::MinusMinus)
+      && (!is_assignable(&expr) || self.check_next_operand())
+    {
+      self.split_current_minus_minus();
+      return Some(expr);
+    }
+    if self.match_kind_in(&[TokenKind::PlusPlus, TokenKind::MinusMinus]) {
+      let op_token = self.prev().unwrap().to_owned();
+      return self.update(expr, op_token, None, true);
+    }
 
-    self.call()
+    Some(expr)
   }
 
   /// ```
-  /// call → primary ( "(" arguments? ")" )* ;
+  /// call → primary ( "(" arguments? ")" | "." IDENTIFIER | "[" expression "]" )* ;
   /// ```
   fn call(&mut self) -> Option<Expr> {
     let mut expr = self.primary()?;
@@ -197,6 +345,23 @@ impl<'src> Parser<'src> {
     loop {
       if self.match_kind(TokenKind::OpenParen) {
         expr = self.finish_call(expr.clone())?;
+      } else if self.match_kind(TokenKind::Dot) {
+        let name =
+          self.consume_by_kind(TokenKind::Identifier, "Expect property name after '.'.")?;
+        let name: STR = (name.val, name.tag.span).into();
+        expr = Expr::Get {
+          span: expr.span().to(name.1),
+          obj: expr.into(),
+          name,
+        };
+      } else if self.match_kind(TokenKind::OpenBracket) {
+        let index = self.expression()?;
+        self.consume_by_kind(TokenKind::CloseBracket, "Expect ']' after index.")?;
+        expr = Expr::Index {
+          span: expr.span().to(self.prev_span()),
+          obj: expr.into(),
+          index: index.into(),
+        };
       } else {
         break;
       }
@@ -214,12 +379,8 @@ impl<'src> Parser<'src> {
     if !self.check_kind(TokenKind::CloseParen) {
       loop {
         if args.len() >= MAX_ARGS {
-          self.had_parsing_error = true;
-          report_token(
-            self.curr_line,
-            self.peek(),
-            &format!("Cannot have more than {} arguments.", MAX_ARGS),
-          );
+          let (found, span) = self.locate(self.peek().copied());
+          self.report(ParseError::TooManyArguments { found, span });
           // TODO: `return None` or `break`?
           return None;
         }
@@ -233,6 +394,7 @@ impl<'src> Parser<'src> {
     self.consume_by_kind(TokenKind::CloseParen, "Expect ')' after arguments.")?;
 
     Expr::Call {
+      span: callee.span().to(self.prev_span()),
       callee: callee.into(),
       args,
     }
@@ -240,52 +402,90 @@ impl<'src> Parser<'src> {
   }
 
   /// ```
-  /// primary → "true" | "false" | "nil"
-  ///         |  NUMBER | STRING
+  /// primary → "true" | "false" | "nil" | "this"
+  ///         |  NUMBER | STRING | interpolation
   ///         |  "(" expression ")"
-  ///         |  IDENTIFIER ;   
+  ///         |  "[" arguments? "]"
+  ///         |  "{" ( entry ( "," entry )* )? "}"
+  ///         |  IDENTIFIER
+  ///         |  "super" "." IDENTIFIER
+  ///         |  "fun" "(" functionBody ;
   /// ```
   fn primary(&mut self) -> Option<Expr> {
     use LiteralKind::*;
     use TokenKind::*;
 
+    if self.match_token(Token::fun_tok()) {
+      let start = self.prev_span();
+      self.consume_by_kind(OpenParen, "Expect '(' after 'fun'.")?;
+      let (params, body) = self.function_body("function")?;
+      return Expr::Lambda {
+        params,
+        body,
+        span: start.to(self.prev_span()),
+      }
+      .into();
+    }
     if self.match_token(Token::true_tok()) {
+      let span = self.prev_span();
       return Expr::Literal {
-        val: (true.into(), self.curr_line).into(),
+        val: (true.into(), span).into(),
+        span,
       }
       .into();
     }
     if self.match_token(Token::false_tok()) {
+      let span = self.prev_span();
       return Expr::Literal {
-        val: (false.into(), self.curr_line).into(),
+        val: (false.into(), span).into(),
+        span,
       }
       .into();
     }
     if self.match_token(Token::nil_tok()) {
+      let span = self.prev_span();
       return Expr::Literal {
-        val: (Option::<f64>::None.into(), self.curr_line).into(),
+        val: (Option::<f64>::None.into(), span).into(),
+        span,
       }
       .into();
     }
 
     if self.match_kind(Literal { kind: Number }) {
       let num = self.prev().unwrap().val;
+      let span = self.prev_span();
       return Expr::Literal {
-        val: (
-          num.parse::<f64>().unwrap_or_default().into(),
-          self.curr_line,
-        )
-          .into(),
+        val: (parse_number(num).into(), span).into(),
+        span,
       }
       .into();
     }
     if self.match_kind(Literal { kind: Str }) {
       // This should contains `"` at the start and end.
-      let string = self.prev().unwrap().val;
-      debug_assert!(string.starts_with('"') && string.ends_with('"'));
+      return self.string_segment().into();
+    }
+    if self.match_kind(Literal { kind: StrHead }) {
+      return self.interpolation();
+    }
 
-      return Expr::Literal {
-        val: (string[1..string.len() - 1].into(), self.curr_line).into(),
+    if self.match_token(Token::this_tok()) {
+      let keyword = self.prev().unwrap();
+      return Expr::This {
+        keyword: (keyword.val, keyword.tag.span).into(),
+        depth: Default::default(),
+        span: keyword.tag.span,
+      }
+      .into();
+    }
+    if self.match_token(Token::super_tok()) {
+      let keyword = self.prev().unwrap().to_owned();
+      self.consume_by_kind(TokenKind::Dot, "Expect '.' after 'super'.")?;
+      let method = self.consume_by_kind(TokenKind::Identifier, "Expect superclass method name.")?;
+      return Expr::Super {
+        keyword: (keyword.val, keyword.tag.span).into(),
+        method: (method.val, method.tag.span).into(),
+        depth: Default::default(),
+        span: keyword.tag.span.to(method.tag.span),
       }
       .into();
     }
@@ -293,18 +493,143 @@ impl<'src> Parser<'src> {
     if self.match_non_keyword_identifier() {
       let name = self.prev()?;
       return Expr::Var {
-        name: (name.val, name.tag.line).into(),
+        name: (name.val, name.tag.span).into(),
+        depth: Default::default(),
+        span: name.tag.span,
       }
       .into();
     }
     if self.match_kind(TokenKind::OpenParen) {
+      let start = self.prev_span();
       let expr = self.expression()?;
       self.consume_by_kind(TokenKind::CloseParen, "Expect ')' after expression.")?;
-      return Expr::Grouping { expr: expr.into() }.into();
+      return Expr::Grouping {
+        expr: expr.into(),
+        span: start.to(self.prev_span()),
+      }
+      .into();
+    }
+    if self.match_kind(TokenKind::OpenBracket) {
+      let start = self.prev_span();
+      let mut elements = vec![];
+      if !self.check_kind(TokenKind::CloseBracket) {
+        loop {
+          elements.push(self.expression()?);
+          if !self.match_kind(TokenKind::Comma) {
+            break;
+          }
+        }
+      }
+      self.consume_by_kind(TokenKind::CloseBracket, "Expect ']' after list elements.")?;
+      return Expr::List {
+        elements,
+        span: start.to(self.prev_span()),
+      }
+      .into();
     }
 
-    self.had_parsing_error = true;
-    report_token(self.curr_line, self.peek(), "Expect expression.");
+    // Blocks are statements, so a brace here always starts a map.
+    if self.match_kind(TokenKind::OpenBrace) {
+      let start = self.prev_span();
+      let mut entries = vec![];
+      if !self.check_kind(TokenKind::CloseBrace) {
+        loop {
+          entries.push(self.map_entry()?);
+          if !self.match_kind(TokenKind::Comma) {
+            break;
+          }
+        }
+      }
+      self.consume_by_kind(TokenKind::CloseBrace, "Expect '}' after map entries.")?;
+      return Expr::Map {
+        entries,
+        span: start.to(self.prev_span()),
+      }
+      .into();
+    }
+
+    let (found, span) = self.locate(self.peek().copied());
+    self.report(ParseError::ExpectedExpression { found, span });
     None
   }
+
+  /// The literal value of the previous string (or segment of it).
+  fn string_segment(&mut self) -> Expr {
+    let string = parse_string(self.prev().unwrap().val);
+    let span = self.prev_span();
+    Expr::Literal {
+      val: (string.as_str().into(), span).into(),
+      span,
+    }
+  }
+
+  /// ```
+  /// interpolation → STRING_HEAD expression ( STRING_MIDDLE expression )* STRING_TAIL ;
+  /// ```
+  fn interpolation(&mut self) -> Option<Expr> {
+    let start = self.prev_span();
+    let mut parts = vec![self.string_segment()];
+    loop {
+      parts.push(self.expression()?);
+      if self.match_kind(TokenKind::Literal {
+        kind: LiteralKind::StrMiddle,
+      }) {
+        parts.push(self.string_segment());
+      } else {
+        let tail = TokenKind::Literal {
+          kind: LiteralKind::StrTail,
+        };
+        self.consume_by_kind(tail, "Expect '}' after interpolation.")?;
+        parts.push(self.string_segment());
+        break;
+      }
+    }
+    // Empty segments (e.g. around `"${a}${b}"`) are left out.
+    parts.retain(|part| {
+      !matches!(part, Expr::Literal { val: PosedLiteral(LiteralValue::String(s), _), .. } if s.is_empty())
+    });
+    Expr::Interpolation {
+      parts,
+      span: start.to(self.prev_span()),
+    }
+    .into()
+  }
+
+  /// ```
+  /// entry → expression ":" expression ;
+  /// ```
+  fn map_entry(&mut self) -> Option<(Expr, Expr)> {
+    let key = self.expression()?;
+    self.consume_by_kind(TokenKind::Colon, "Expect ':' after map key.")?;
+    let val = self.expression()?;
+    Some((key, val))
+  }
+}
+
+/// Whether `expr` could be the target of an assignment or update.
+fn is_assignable(expr: &Expr) -> bool {
+  matches!(
+    expr,
+    Expr::Var { .. } | Expr::Get { .. } | Expr::Index { .. }
+  )
+}
+
+/// `-right`, where `op_span` is the span of `-`.
+fn negate(op_span: Span, right: Expr) -> Expr {
+  Expr::Unary {
+    op: (Operator::Minus, op_span).into(),
+    span: op_span.to(right.span()),
+    right: right.into(),
+  }
+}
+
+/// Spans of the two `-` in the `span` of `--`.
+pub(super) fn split_minus_minus(span: Span) -> [Span; 2] {
+  let Span {
+    start, line, col, ..
+  } = span;
+  [
+    Span::new(start, start + 1, line, col),
+    Span::new(start + 1, start + 2, line, col + 1),
+  ]
 }
@@ -1,14 +1,14 @@
-use crate::{
-  error::{report_detail, report_token},
-  Token, RESERVED_WORDS,
-};
-use ember_lox_ast::ast::prelude::*;
+use crate::{error::ParseError, parse_number, parse_string, Token, RESERVED_WORDS};
+use ember_lox_ast::{ast::prelude::*, STR};
 use ember_lox_tokenizer::prelude::*;
 
 pub mod expr;
 pub mod stmt;
 pub mod util;
 
+/// Maximum number of arguments (of a call) or parameters (of a function).
+pub(crate) const MAX_ARGS: usize = u8::MAX as usize;
+
 #[derive(Debug, Clone)]
 pub struct Parser<'src> {
   /// `May` include [TokenizationError].
@@ -19,34 +19,78 @@ pub struct Parser<'src> {
   curr_token: usize,
   /// Current `line` number
   curr_line: usize,
-  /// Whether the parser has encountered a parsing error.
-  had_parsing_error: bool,
+  /// How many loop bodies the parser is currently inside, reset within function bodies.
+  loop_depth: usize,
+  /// Errors found so far, including the ones from tokenization.
+  errors: Vec<ParseError>,
 }
 
 impl<'src> Parser<'src> {
   pub fn had_parsing_error(&self) -> bool {
-    self.had_parsing_error
+    !self.errors.is_empty()
+  }
+
+  fn report(&mut self, error: ParseError) {
+    self.errors.push(error);
+  }
+
+  /// Lexeme and span of `token`, `None` means the end of input.
+  fn locate(&self, token: Option<Token<'src>>) -> (Option<String>, Span) {
+    match token {
+      Some(t) => (Some(t.val.to_string()), t.tag.span),
+      None => (None, self.end_span()),
+    }
   }
 
-  fn report_err_token(&self, err_msg: &str) {
+  /// Where a missing token is reported.
+  fn expected_at(&self) -> Option<Token<'src>> {
     // We assume that `self.peek()`'s worst case is to get `None`.
-    let mut err_token = self.peek();
+    let mut err_token = self.peek().copied();
     if let Some(e) = err_token {
       // However, in this case, we get the first next-line token, ignored the end of line
       if e.tag.line != self.curr_line {
         // Thus, we need to look back to the previous token.
-        err_token = self.prev();
+        err_token = self.prev().copied();
       }
     }
-    report_detail(self.curr_line, err_token.map(|e| e.val), err_msg);
+    err_token
+  }
+
+  fn report_expected(&mut self, expected: TokenKind, err_msg: &str) {
+    let (found, span) = self.locate(self.expected_at());
+    self.report(ParseError::ExpectedToken {
+      expected,
+      message: err_msg.to_string(),
+      found,
+      span,
+    });
+  }
+
+  /// An empty span right after the last token.
+  fn end_span(&self) -> Span {
+    let Some(last) = self.tokens.last() else {
+      return Span::new(0, 0, self.curr_line, 1);
+    };
+    let span = last.tag.span;
+    let col = span.col + last.val.chars().count();
+    Span::new(span.end, span.end, span.line, col)
   }
 
   fn consume_by_kind(&mut self, kind: TokenKind, err_msg: &str) -> Option<&Token<'src>> {
     if self.check_kind(kind) {
       return self.advance();
     }
-    self.had_parsing_error = true;
-    self.report_err_token(err_msg);
+    self.report_expected(kind, err_msg);
+    None
+  }
+
+  /// Like [Parser::consume_by_kind], but a reserved word isn't taken as an identifier,
+  /// e.g. the name of a declaration.
+  fn consume_non_keyword_identifier(&mut self, err_msg: &str) -> Option<&Token<'src>> {
+    if self.check_non_keyword_identifier() {
+      return self.advance();
+    }
+    self.report_expected(TokenKind::Identifier, err_msg);
     None
   }
 
@@ -55,8 +99,7 @@ impl<'src> Parser<'src> {
     if self.check_token(token) {
       return self.advance();
     }
-    self.had_parsing_error = true;
-    self.report_err_token(err_msg);
+    self.report_expected(token.tag.kind, err_msg);
     None
   }
 
@@ -100,6 +143,20 @@ impl<'src> Parser<'src> {
     }
     token
   }
+  /// Replaces the current `--` token with two `-` tokens.
+  fn split_current_minus_minus(&mut self) {
+    let token = self.tokens[self.curr_token];
+    debug_assert_eq!(token.tag.kind, TokenKind::MinusMinus);
+    let minus = |i: usize, span| Token {
+      tag: TagToken::new(TokenKind::Minus, 1, token.tag.line, span),
+      val: &token.val[i..i + 1],
+    };
+    let [first, second] = expr::split_minus_minus(token.tag.span);
+    self.tokens.splice(
+      self.curr_token..=self.curr_token,
+      [minus(0, first), minus(1, second)],
+    );
+  }
   /// Returns the current token we have yet to consume
   fn peek(&self) -> Option<&Token<'src>> {
     self.tokens.get(self.curr_token)
@@ -108,6 +165,10 @@ impl<'src> Parser<'src> {
   fn prev(&self) -> Option<&Token<'src>> {
     self.tokens.get(self.curr_token - 1)
   }
+  /// Returns the span of the most recently consumed token.
+  fn prev_span(&self) -> Span {
+    self.prev().map(|t| t.tag.span).unwrap_or_default()
+  }
   /// Returns `true` if the parser has consumed all tokens.
   fn is_at_end(&self) -> bool {
     self.peek().is_none()
@@ -122,6 +183,28 @@ impl<'src> Parser<'src> {
   fn check_kind_in(&self, token_kinds: &[TokenKind]) -> bool {
     token_kinds.iter().any(|&k| self.check_kind(k))
   }
+  /// Like [Parser::check_kind], but for the token after the current one.
+  fn check_next_kind(&self, token_kind: TokenKind) -> bool {
+    self
+      .tokens
+      .get(self.curr_token + 1)
+      .is_some_and(|t| t.tag.kind == token_kind)
+  }
+  /// Returns `true` if the token after the current one can only start an operand,
+  /// e.g. `b` in `a--b`, but not `-` in `a-- - b`.
+  fn check_next_operand(&self) -> bool {
+    self
+      .tokens
+      .get(self.curr_token + 1)
+      .is_some_and(|t| match t.tag.kind {
+        TokenKind::Identifier => !matches!(t.val, "and" | "or"),
+        TokenKind::Literal { .. }
+        | TokenKind::OpenParen
+        | TokenKind::OpenBracket
+        | TokenKind::Bang => true,
+        _ => false,
+      })
+  }
   /// Returns `true` if the current token is of the given one.
   ///
   /// Unlike [Parser::match_kind] this method does not consume the token.
@@ -180,12 +263,24 @@ impl<'src> Parser<'src> {
 }
 
 impl<'src> Parser<'src> {
+  /// Tokenization errors are recorded, and won't be seen by the parser.
   pub fn new(token_stream: impl Iterator<Item = Token<'src>>) -> Self {
+    let mut errors = vec![];
+    let tokens = token_stream
+      .filter(|t| match t.lexical_error() {
+        Some(e) => {
+          errors.push(e);
+          false
+        }
+        None => true,
+      })
+      .collect();
     Self {
-      tokens: token_stream.collect(),
+      tokens,
       curr_token: 0,
-      had_parsing_error: false,
       curr_line: 1,
+      loop_depth: 0,
+      errors,
     }
   }
 
@@ -193,19 +288,17 @@ impl<'src> Parser<'src> {
   /// ```
   /// program → declaration* EOF ;
   /// ```
-  pub fn parse(&mut self) -> Option<Vec<Stmt>> {
+  ///
+  /// Returns all errors found, if any.
+  pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
     let mut stmts = vec![];
     while !self.is_at_end() {
       stmts.push(self.declaration());
     }
-    if self.had_parsing_error {
-      None
+    if self.had_parsing_error() {
+      Err(std::mem::take(&mut self.errors))
     } else {
-      stmts
-        .into_iter()
-        .filter_map(|stmt| stmt)
-        .collect::<Vec<_>>()
-        .into()
+      Ok(stmts.into_iter().flatten().collect())
     }
   }
 }
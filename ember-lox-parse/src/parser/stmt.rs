@@ -3,9 +3,12 @@ use super::*;
 impl<'src> Parser<'src> {
   /// ```
   /// statement → exprStmt
+  ///           |  breakStmt
+  ///           |  continueStmt
   ///           |  forStmt
   ///           |  ifStmt
   ///           |  printStmt
+  ///           |  returnStmt
   ///           |  whileStmt
   ///           |  block ;
   /// ```
@@ -19,12 +22,20 @@ impl<'src> Parser<'src> {
     if self.match_token(Token::print_tok()) {
       return self.print_stmt();
     }
+    if self.match_token(Token::return_tok()) {
+      return self.return_stmt();
+    }
     if self.match_token(Token::while_tok()) {
       return self.while_stmt();
     }
+    if self.match_token(Token::break_tok()) || self.match_token(Token::continue_tok()) {
+      return self.loop_control_stmt();
+    }
     if self.match_kind(TokenKind::OpenBrace) {
+      let start = self.prev_span();
       return Stmt::Block {
         stmts: self.block()?,
+        span: start.to(self.prev_span()),
       }
       .into();
     }
@@ -38,6 +49,7 @@ impl<'src> Parser<'src> {
   ///            expression? ")" statement ;
   /// ```
   fn for_stmt(&mut self) -> Option<Stmt> {
+    let start = self.prev_span();
     self.consume_by_kind(TokenKind::OpenParen, "Expect '(' after 'for'.")?;
 
     let initializer = if self.match_token(Token::var_tok()) {
@@ -62,36 +74,20 @@ impl<'src> Parser<'src> {
     };
     self.consume_by_kind(TokenKind::CloseParen, "Expect ')' after for clauses.");
 
-    // manual de-sugaring of the for loop
-    let mut body = self.statement()?;
-    // increment the loop variable
-    if let Some(increment) = increment {
-      body = Stmt::Block {
-        stmts: vec![body, Stmt::Expression { expr: increment }.into()],
-      }
-      .into();
-    }
-    // check the loop condition
-    body = Stmt::While {
-      cond: cond.map_or(
-        // if no condition, then set it as true
-        Expr::Literal {
-          val: (true.into(), self.curr_line).into(),
-        },
-        |c| c,
-      ),
+    let body = self.loop_body()?;
+
+    Stmt::For {
+      initializer: initializer.map(Box::new),
+      // if no condition, then set it as true (located at `for`)
+      cond: cond.unwrap_or(Expr::Literal {
+        val: (true.into(), start).into(),
+        span: start,
+      }),
+      increment,
       body: body.into(),
+      span: start.to(self.prev_span()),
     }
-    .into();
-    // initialize the loop variable
-    if let Some(initializer) = initializer {
-      body = Stmt::Block {
-        stmts: vec![initializer, body],
-      }
-      .into();
-    }
-
-    body.into()
+    .into()
   }
 
   /// ```
@@ -99,6 +95,7 @@ impl<'src> Parser<'src> {
   ///         ( "else" statement )? ;
   /// ```
   fn if_stmt(&mut self) -> Option<Stmt> {
+    let start = self.prev_span();
     self.consume_by_kind(TokenKind::OpenParen, "Expect '(' after 'if'.")?;
     let cond = self.expression()?;
     self.consume_by_kind(TokenKind::CloseParen, "Expect ')' after if condition.");
@@ -112,7 +109,8 @@ impl<'src> Parser<'src> {
     Stmt::If {
       cond,
       then_branch: then_branch.into(),
-      else_branch: Some(else_branch?.into()),
+      else_branch: else_branch.map(Box::new),
+      span: start.to(self.prev_span()),
     }
     .into()
   }
@@ -121,19 +119,56 @@ impl<'src> Parser<'src> {
   /// whileStmt → "while" "(" expression ")" statement ;
   /// ```
   fn while_stmt(&mut self) -> Option<Stmt> {
+    let start = self.prev_span();
     self.consume_by_kind(TokenKind::OpenParen, "Expect '(' after 'while'.")?;
     let cond = self.expression()?;
     self.consume_by_kind(TokenKind::CloseParen, "Expect ')' after while condition.");
 
-    let body = self.statement()?;
+    let body = self.loop_body()?;
 
     Stmt::While {
       cond,
       body: body.into(),
+      span: start.to(self.prev_span()),
     }
     .into()
   }
 
+  /// The body of `while` or `for`, in which `break` and `continue` are allowed.
+  fn loop_body(&mut self) -> Option<Stmt> {
+    self.loop_depth += 1;
+    let body = self.statement();
+    self.loop_depth -= 1;
+    body
+  }
+
+  /// ```
+  /// breakStmt    → "break" ";" ;
+  /// continueStmt → "continue" ";" ;
+  /// ```
+  fn loop_control_stmt(&mut self) -> Option<Stmt> {
+    let keyword = self.prev().unwrap().to_owned();
+    let is_break = keyword.val == "break";
+    if self.loop_depth == 0 {
+      // Report it, but keep parsing the rest of the statement.
+      self.report(ParseError::OutsideLoop {
+        keyword: if is_break { "break" } else { "continue" },
+        span: keyword.tag.span,
+      });
+    }
+    self.consume_by_kind(
+      TokenKind::Semi,
+      &format!("Expect ';' after '{}'.", keyword.val),
+    )?;
+
+    let span = keyword.tag.span.to(self.prev_span());
+    if is_break {
+      Stmt::Break { span }.into()
+    } else {
+      Stmt::Continue { span }.into()
+    }
+  }
+
   /// ```
   /// block → "{" declaration* "}" ;
   /// ```
@@ -162,44 +197,197 @@ impl<'src> Parser<'src> {
   fn expr_stmt(&mut self) -> Option<Stmt> {
     let expr = self.expression()?;
     self.consume_by_kind(TokenKind::Semi, "Expect ';' after expression.")?;
-    Stmt::Expression { expr }.into()
+    Stmt::Expression {
+      span: expr.span().to(self.prev_span()),
+      expr,
+    }
+    .into()
   }
 
   /// ```
-  /// declaration → funDecl
+  /// declaration → classDecl
+  ///             |  funDecl
   ///             |  varDecl
   ///             |  statement ;
   /// ```
   pub(crate) fn declaration(&mut self) -> Option<Stmt> {
-    if self.match_token(Token::var_tok()) {
-      let var = self.var_decl();
-      if var.is_none() {
-        self.synchronize();
-        return None;
-      }
-      return var; // Remember to return the `var_decl` statement.
-    }
-    let stmt = self.statement();
-    if stmt.is_none() {
+    let decl = if self.match_token(Token::class_tok()) {
+      self.class_decl()
+    // Otherwise, `fun (` starts a lambda in an expression statement.
+    } else if self.check_next_kind(TokenKind::Identifier) && self.match_token(Token::fun_tok()) {
+      self.fun_decl()
+    } else if self.match_token(Token::var_tok()) {
+      self.var_decl()
+    } else {
+      self.statement()
+    };
+    if decl.is_none() {
       self.synchronize();
       return None;
     }
-    stmt
+    decl
+  }
+
+  /// ```
+  /// classDecl → "class" IDENTIFIER ( "<" IDENTIFIER )?
+  ///              "{" function* "}" ;
+  /// ```
+  fn class_decl(&mut self) -> Option<Stmt> {
+    let start = self.prev_span();
+    let name = self
+      .consume_non_keyword_identifier("Expect class name.")?
+      .clone();
+    let name: STR = (name.val, name.tag.span).into();
+
+    let superclass = if self.match_kind(TokenKind::Lt) {
+      let super_name = self
+        .consume_non_keyword_identifier("Expect superclass name.")?
+        .clone();
+      let super_name: STR = (super_name.val, super_name.tag.span).into();
+      // The superclass is looked up just like a variable.
+      let lookup = Expr::Var {
+        name: super_name.clone(),
+        depth: Default::default(),
+        span: super_name.1,
+      };
+      Some((super_name, Some(lookup)))
+    } else {
+      None
+    };
+
+    self.consume_by_kind(TokenKind::OpenBrace, "Expect '{' before class body.")?;
+    let mut methods = vec![];
+    while !self.check_kind(TokenKind::CloseBrace) && !self.is_at_end() {
+      methods.push(self.function("method")?);
+    }
+    self.consume_by_kind(TokenKind::CloseBrace, "Expect '}' after class body.")?;
+
+    Stmt::Class {
+      name,
+      superclass,
+      methods,
+      span: start.to(self.prev_span()),
+    }
+    .into()
+  }
+
+  /// ```
+  /// funDecl → "fun" function ;
+  /// ```
+  fn fun_decl(&mut self) -> Option<Stmt> {
+    let start = self.prev_span();
+    let (name, params, body) = self.function("function")?;
+    Stmt::Function {
+      name,
+      params,
+      body,
+      span: start.to(self.prev_span()),
+    }
+    .into()
+  }
+
+  /// ```
+  /// function → IDENTIFIER "(" functionBody ;
+  /// ```
+  ///
+  /// `kind` is only used in error messages (e.g. `function`, `method`).
+  pub(crate) fn function(&mut self, kind: &str) -> Option<FunctionFiled> {
+    let name = self
+      .consume_non_keyword_identifier(&format!("Expect {} name.", kind))?
+      .clone();
+    self.consume_by_kind(
+      TokenKind::OpenParen,
+      &format!("Expect '(' after {} name.", kind),
+    )?;
+    let (params, body) = self.function_body(kind)?;
+
+    Some(((name.val, name.tag.span).into(), params, body))
+  }
+
+  /// ```
+  /// functionBody → parameters? ")" block ;
+  /// ```
+  ///
+  /// Shared by named functions and lambdas, after the `(`.
+  pub(crate) fn function_body(&mut self, kind: &str) -> Option<(Vec<STR>, Vec<Stmt>)> {
+    let params = self.parameters()?;
+    self.consume_by_kind(TokenKind::CloseParen, "Expect ')' after parameters.")?;
+
+    self.consume_by_kind(
+      TokenKind::OpenBrace,
+      &format!("Expect '{{' before {} body.", kind),
+    )?;
+    // Loops outside of the function can't be broken from inside.
+    let loop_depth = std::mem::take(&mut self.loop_depth);
+    let body = self.block();
+    self.loop_depth = loop_depth;
+
+    Some((params, body?))
+  }
+
+  /// ```
+  /// parameters → IDENTIFIER ( "," IDENTIFIER )* ;
+  /// ```
+  fn parameters(&mut self) -> Option<Vec<STR>> {
+    let mut params = vec![];
+
+    if !self.check_kind(TokenKind::CloseParen) {
+      loop {
+        if params.len() >= MAX_ARGS {
+          let (found, span) = self.locate(self.peek().copied());
+          self.report(ParseError::TooManyParameters { found, span });
+          return None;
+        }
+        let param = self.consume_non_keyword_identifier("Expect parameter name.")?;
+        params.push((param.val, param.tag.span).into());
+        if !self.match_kind(TokenKind::Comma) {
+          break;
+        }
+      }
+    }
+
+    params.into()
   }
 
   /// ```
   /// printStmt → "print" expression ";" ;
   /// ```
   fn print_stmt(&mut self) -> Option<Stmt> {
+    let start = self.prev_span();
     let expr = self.expression()?;
     self.consume_by_kind(TokenKind::Semi, "Expect ';' after value.")?;
-    Stmt::Print { expr }.into()
+    Stmt::Print {
+      expr,
+      span: start.to(self.prev_span()),
+    }
+    .into()
+  }
+
+  /// ```
+  /// returnStmt → "return" expression? ";" ;
+  /// ```
+  fn return_stmt(&mut self) -> Option<Stmt> {
+    let keyword = self.prev().unwrap().to_owned();
+    let value = if !self.check_kind(TokenKind::Semi) {
+      Some(self.expression()?)
+    } else {
+      None
+    };
+    self.consume_by_kind(TokenKind::Semi, "Expect ';' after return value.")?;
+
+    Stmt::Return {
+      keyword: (keyword.val.into(), keyword.tag.span).into(),
+      value,
+      span: keyword.tag.span.to(self.prev_span()),
+    }
+    .into()
   }
 
   /// ```
   /// varDecl → "var" IDENTIFIER ( "=" expression )? ";" ;
   /// ```
   fn var_decl(&mut self) -> Option<Stmt> {
+    let start = self.prev_span();
     let name = self
       .consume_by_kind(TokenKind::Identifier, "Expect variable name")?
       .clone();
@@ -212,8 +400,9 @@ impl<'src> Parser<'src> {
 
     self.consume_by_kind(TokenKind::Semi, "Expect ';' after variable declaration.")?;
     Stmt::Variable {
-      name: (name.val, name.tag.line).into(),
+      name: (name.val, name.tag.span).into(),
       initializer,
+      span: start.to(self.prev_span()),
     }
     .into()
   }
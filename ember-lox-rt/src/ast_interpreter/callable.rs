@@ -0,0 +1,142 @@
+//! Callable objects of the `tree-walk` interpreter.
+
+use super::{environment::Env, CallFrame, Flow, Interpreter, Unwind};
+use crate::{error::ErrorKind, native::NativeFunction, runtime::FRAMES_MAX, value::Value};
+use ember_lox_ast::{ast::prelude::*, STR};
+use ember_lox_tokenizer::Span;
+use std::rc::Rc;
+
+/// If less native stack than this is left, a call runs on a new stack segment.
+const STACK_RED_ZONE: usize = 256 * 1024;
+/// Size of each new stack segment.
+const STACK_SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+pub trait Callable {
+  /// Number of arguments the callable expects.
+  fn arity(&self) -> usize;
+
+  /// Calls with already evaluated `args`, whose length equals to [Callable::arity].
+  ///
+  /// `call_site` is where the call happens, which is recorded in the trace of errors.
+  fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>, call_site: Span) -> Flow;
+}
+
+/// A user-defined function.
+#[derive(Debug)]
+pub struct LoxFunction {
+  name: STR,
+  /// Shared with the bound copies of this function.
+  params: Rc<[STR]>,
+  /// Shared with the bound copies of this function.
+  body: Rc<[Stmt]>,
+  /// The environment where the function is declared.
+  closure: Env,
+  /// Whether it's the `init` method of a class.
+  is_initializer: bool,
+  /// The instance bound as `this`, `None` if it isn't a bound method.
+  this: Option<Value>,
+}
+
+impl LoxFunction {
+  pub fn new(
+    name: STR,
+    params: Vec<STR>,
+    body: Vec<Stmt>,
+    closure: Env,
+    is_initializer: bool,
+  ) -> Self {
+    Self {
+      name,
+      params: params.into(),
+      body: body.into(),
+      closure,
+      is_initializer,
+      this: None,
+    }
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name.0
+  }
+
+  /// Creates a method bound to `instance`, which could be accessed as `this`.
+  pub fn bind(&self, instance: Value) -> LoxFunction {
+    let mut closure = self.closure.clone();
+    closure.new_enclosed();
+    closure.define("this".into(), instance.clone());
+    LoxFunction {
+      name: self.name.clone(),
+      params: self.params.clone(),
+      body: self.body.clone(),
+      closure,
+      is_initializer: self.is_initializer,
+      this: Some(instance),
+    }
+  }
+
+  /// Whether both are the same method bound to the same instance.
+  ///
+  /// Each access to a method binds a new copy, so they can't be compared by identity.
+  pub fn is_same_bound_method(&self, other: &LoxFunction) -> bool {
+    match (&self.this, &other.this) {
+      (Some(a), Some(b)) => Rc::ptr_eq(&self.body, &other.body) && a == b,
+      _ => false,
+    }
+  }
+}
+
+impl Callable for LoxFunction {
+  fn arity(&self) -> usize {
+    self.params.len()
+  }
+
+  fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>, call_site: Span) -> Flow {
+    // The top-level script takes one frame, as in the `bytecode` backend.
+    if interpreter.frames.len() + 1 >= FRAMES_MAX {
+      return interpreter.error(ErrorKind::StackOverflow, call_site);
+    }
+
+    // Parameters and the body share the same (new) scope.
+    let mut env = self.closure.clone();
+    env.new_enclosed();
+    for (param, arg) in self.params.iter().zip(args) {
+      env.define(param.0.to_owned(), arg);
+    }
+
+    interpreter.frames.push(CallFrame {
+      function: self.name.clone(),
+      call_site,
+    });
+    // Every call takes plenty of native frames, so the native stack grows on demand,
+    // and only `FRAMES_MAX` limits the depth.
+    let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+      interpreter.execute_block_in(&self.body, env)
+    });
+    interpreter.frames.pop();
+
+    let returned = match result {
+      // Falling off the end of the body returns `nil`.
+      Ok(()) => Value::nil(),
+      Err(Unwind::Return(value)) => value,
+      Err(e) => return Err(e),
+    };
+    // An initializer always returns `this`.
+    if self.is_initializer {
+      return Ok(self.closure.get_at(Some(0), "this").unwrap_or(returned));
+    }
+    Ok(returned)
+  }
+}
+
+impl Callable for NativeFunction {
+  fn arity(&self) -> usize {
+    NativeFunction::arity(self)
+  }
+
+  fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>, call_site: Span) -> Flow {
+    match NativeFunction::call(self, &args) {
+      Ok(value) => Ok(value),
+      Err(message) => interpreter.error(ErrorKind::Native(message), call_site),
+    }
+  }
+}
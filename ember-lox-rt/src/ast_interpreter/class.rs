@@ -0,0 +1,45 @@
+//! Classes and instances of the `tree-walk` interpreter.
+
+use super::{callable::Callable, Flow, Interpreter};
+use crate::{
+  object::{LoxClass, LoxInstance},
+  value::Value,
+};
+use ember_lox_tokenizer::Span;
+use std::rc::Rc;
+
+/// Calling a class constructs a new instance of it.
+impl Callable for Rc<LoxClass> {
+  fn arity(&self) -> usize {
+    match self.find_method("init") {
+      Some(Value::Function(init)) => init.arity(),
+      _ => 0,
+    }
+  }
+
+  fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>, call_site: Span) -> Flow {
+    let instance = Value::Instance(Rc::new(LoxInstance::new(self.clone())));
+    if let Some(Value::Function(init)) = self.find_method("init") {
+      init
+        .bind(instance.clone())
+        .call(interpreter, args, call_site)?;
+    }
+    Ok(instance)
+  }
+}
+
+/// Fields shadow methods. A found method will be bound to `this`.
+pub(crate) fn get_property(instance: &Rc<LoxInstance>, name: &str) -> Option<Value> {
+  if let Some(field) = instance.field(name) {
+    return Some(field);
+  }
+  bind_method(instance.class(), name, Value::Instance(instance.clone()))
+}
+
+/// Finds the method from `class`, and binds it to `this`.
+pub(crate) fn bind_method(class: &LoxClass, name: &str, this: Value) -> Option<Value> {
+  match class.find_method(name)? {
+    Value::Function(method) => Some(Value::Function(method.bind(this).into())),
+    _ => None,
+  }
+}
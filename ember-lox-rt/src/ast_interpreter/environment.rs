@@ -1,8 +1,7 @@
-use dashmap::{mapref::one::Ref, DashMap};
-use ember_lox_ast::ast::prelude::*;
-use std::{collections::VecDeque, sync::Arc};
+use crate::value::Value;
+use dashmap::DashMap;
+use std::{collections::VecDeque, rc::Rc, sync::Arc};
 
-pub type Value = LiteralValue;
 type STR = Arc<str>;
 
 #[derive(Debug, Default, Clone)]
@@ -10,10 +9,14 @@ pub struct EnvFrame {
   values: DashMap<STR, Value>,
 }
 
+/// A chain of scopes.
+///
+/// Frames are shared (rather than owned), so that a closure could capture
+/// the chain it is declared in and still observe later definitions in it.
 #[derive(Debug, Clone)]
 pub struct Env {
   /// Direction: `Innermost -> ... -> Outermost`
-  env_chain: VecDeque<EnvFrame>,
+  env_chain: VecDeque<Rc<EnvFrame>>,
 }
 
 impl Default for Env {
@@ -25,34 +28,48 @@ impl Default for Env {
 impl Env {
   pub fn new() -> Self {
     let mut env_chain = VecDeque::new();
-    env_chain.push_front(EnvFrame::default());
+    env_chain.push_front(EnvFrame::default().into());
     Self { env_chain }
   }
 
   pub fn new_enclosed(&mut self) {
-    self.env_chain.push_front(EnvFrame::default());
+    self.env_chain.push_front(EnvFrame::default().into());
   }
 
   pub fn drop_innermost_scope(&mut self) {
     self.env_chain.pop_front();
   }
 
-  pub fn assign(&mut self, name: STR, value: Value) -> Option<Value> {
-    for env_node in &mut self.env_chain {
-      if env_node.values.contains_key(&name) {
-        return env_node.values.insert(name, value);
-      }
+  /// The frame `depth` scopes away from the innermost one, or the globals if `None`.
+  fn frame_at(&self, depth: Option<usize>) -> Option<&Rc<EnvFrame>> {
+    match depth {
+      Some(depth) => self.env_chain.get(depth),
+      None => self.env_chain.back(),
     }
-    None
   }
 
-  pub fn get(&self, name: &str) -> Option<Ref<STR, Value>> {
-    for env_node in &self.env_chain {
-      if let Some(value) = env_node.values.get(name) {
-        return Some(value);
-      }
+  /// Assigns to an existing variable, which has been resolved to `depth`.
+  ///
+  /// Returns the previous value, or `None` if the variable is undefined.
+  pub fn assign_at(&mut self, depth: Option<usize>, name: STR, value: Value) -> Option<Value> {
+    let frame = self.frame_at(depth)?;
+    if !frame.values.contains_key(&name) {
+      return None;
+    }
+    frame.values.insert(name, value)
+  }
+
+  /// Gets the variable, which has been resolved to `depth`.
+  pub fn get_at(&self, depth: Option<usize>, name: &str) -> Option<Value> {
+    let frame = self.frame_at(depth)?;
+    frame.values.get(name).map(|v| v.value().clone())
+  }
+
+  /// Defines the variable in the outermost scope.
+  pub fn define_global(&mut self, name: STR, value: Value) {
+    if let Some(globals) = self.env_chain.back() {
+      globals.values.insert(name, value);
     }
-    None
   }
 
   pub fn define(&mut self, name: STR, value: Value) {
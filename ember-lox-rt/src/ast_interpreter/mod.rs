@@ -3,28 +3,109 @@
 //! `Tree-walk` means that `NO BYTECODE` is generated, it will evaluate everything
 //! recursively (from a valid entry point of `AST`) and return the result.  
 
-use crate::error::report;
+use crate::{
+  error::{ErrorKind, RuntimeError, TraceFrame},
+  native::{self, NativeFunction},
+  object::{LoxClass, LoxList, LoxMap},
+  runtime::Runtime,
+  value::Value,
+  LAMBDA_NAME,
+};
+use callable::{Callable, LoxFunction};
+use class::{bind_method, get_property};
 use ember_lox_ast::{
   ast::prelude::*,
   visit::{Visitor, VisitorAcceptor},
+  STR,
 };
+use ember_lox_tokenizer::Span;
 use environment::Env;
 use std::cmp::{PartialEq, PartialOrd};
-use std::ops::{Add, Div, Mul, Neg, Not, Sub};
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+use std::{collections::HashMap, rc::Rc};
 
+pub mod callable;
+pub mod class;
 pub mod environment;
 
-#[derive(Default)]
+/// Why the execution leaves the normal flow.
+#[derive(Debug)]
+pub enum Unwind {
+  /// A `return` statement, with the returned value.
+  Return(Value),
+  /// `break` and `continue`, caught by the innermost loop.
+  Break,
+  Continue,
+  Error(RuntimeError),
+}
+
+impl Unwind {
+  /// `return` never unwinds out of a function body, only errors could reach the top level.
+  fn into_error(self) -> RuntimeError {
+    match self {
+      Unwind::Error(e) => e,
+      Unwind::Return(_) => unreachable!("`return` should be inside a function"),
+      Unwind::Break | Unwind::Continue => unreachable!("`break` should be inside a loop"),
+    }
+  }
+}
+
+impl From<RuntimeError> for Unwind {
+  fn from(error: RuntimeError) -> Self {
+    Unwind::Error(error)
+  }
+}
+
+/// The result of visiting a node.
+pub type Flow<T = Value> = Result<T, Unwind>;
+
+/// An active call of a [LoxFunction].
+#[derive(Debug)]
+struct CallFrame {
+  function: STR,
+  call_site: Span,
+}
+
 pub struct Interpreter {
   env: Env,
-  has_runtime_error: bool,
+  /// Active calls, innermost last.
+  frames: Vec<CallFrame>,
   is_in_repl: bool,
 }
 
+/// Comes with [native::defaults].
+impl Default for Interpreter {
+  fn default() -> Self {
+    let mut interpreter = Self {
+      env: Env::new(),
+      frames: vec![],
+      is_in_repl: false,
+    };
+    native::defaults()
+      .into_iter()
+      .for_each(|native| interpreter.define_native(native));
+    interpreter
+  }
+}
+
 impl Interpreter {
-  fn runtime_error<T>(&mut self) -> Option<T> {
-    self.has_runtime_error = true;
-    None
+  /// Raises an error at `span`, which records the active calls.
+  fn error<T>(&self, kind: ErrorKind, span: Span) -> Flow<T> {
+    let mut trace = vec![];
+    // Each caller is executing the call of its callee.
+    let mut executing = span;
+    for frame in self.frames.iter().rev() {
+      trace.push(TraceFrame {
+        function: Some(frame.function.0.to_string()),
+        span: executing,
+      });
+      executing = frame.call_site;
+    }
+    trace.push(TraceFrame {
+      function: None,
+      span: executing,
+    });
+    Err(RuntimeError::new(kind, span, trace).into())
   }
 
   pub fn disable_repl_mode(&mut self) {
@@ -35,115 +116,256 @@ impl Interpreter {
     self.is_in_repl = true;
   }
 
-  pub fn evaluate(&mut self, expr: &Expr) -> Option<LiteralValue> {
+  pub fn evaluate(&mut self, expr: &Expr) -> Flow {
     expr.accept(self)
   }
 
-  pub fn interpret(&mut self, roots: &[Stmt], is_in_repl: bool) -> Result<(), ()> {
-    self.is_in_repl = is_in_repl;
-    for root in roots {
-      self.execute(root);
-      if self.has_runtime_error {
-        // Reset the flag for the next run.
-        // (extremely useful in `REPL` mode)
-        if is_in_repl {
-          self.has_runtime_error = false;
-        }
-        self.disable_repl_mode();
-        return Err(());
+  pub fn execute(&mut self, root: &Stmt) -> Flow<()> {
+    root.accept(self).map(|_| ())
+  }
+
+  pub fn execute_block(&mut self, stmts: &[Stmt]) -> Flow<()> {
+    self.env.new_enclosed();
+    let result = self.execute_stmts(stmts);
+    self.env.drop_innermost_scope();
+    result
+  }
+
+  /// Executes `stmts` within the given `env`, then restores the current one.
+  pub(crate) fn execute_block_in(&mut self, stmts: &[Stmt], env: Env) -> Flow<()> {
+    let prev_env = std::mem::replace(&mut self.env, env);
+    let result = self.execute_stmts(stmts);
+    self.env = prev_env;
+    result
+  }
+
+  /// Applies a binary `op` (except the logical ones) to evaluated operands.
+  fn binary_op(&mut self, left: Value, op: &PosedOperator, right: Value) -> Flow {
+    use Operator::*;
+
+    // Equality is defined for every value.
+    match op.0 {
+      Equal => return Ok(left.eq(&right).into()),
+      NotEqual => return Ok(left.ne(&right).into()),
+      _ => {}
+    }
+    // Others are only defined for literals.
+    let (Value::Literal(left), Value::Literal(right)) = (left, right) else {
+      return self.error(ErrorKind::TypeMismatch(OperandError::ExpectNumbers), op.1);
+    };
+    let result = match op.0 {
+      Plus => left.add(&right),
+      Minus => left.sub(&right),
+      Multiply => left.mul(&right),
+      Divide => left.div(&right),
+      Modulo => left.rem(&right),
+      Power => left.pow(&right),
+      Greater => left
+        .check_both_numeric(&right)
+        .map(|(l, r)| l.gt(&r).into()),
+      GreaterEqual => left
+        .check_both_numeric(&right)
+        .map(|(l, r)| l.ge(&r).into()),
+      Less => left
+        .check_both_numeric(&right)
+        .map(|(l, r)| l.lt(&r).into()),
+      LessEqual => left
+        .check_both_numeric(&right)
+        .map(|(l, r)| l.le(&r).into()),
+      _ => unreachable!("invalid binary operator: {}", op.0),
+    };
+    match result {
+      Ok(r) => Ok(r.into()),
+      Err(e) => self.error(ErrorKind::TypeMismatch(e), op.1),
+    }
+  }
+
+  /// Applies the `op` of an [Expr::Update] to the `old` value of its target.
+  fn update_op(&mut self, old: Value, op: &PosedOperator, val: Option<&Expr>) -> Flow {
+    let val = match val {
+      Some(val) => val.accept(self)?,
+      // `++` and `--`
+      None => 1.0.into(),
+    };
+    self.binary_op(old, op, val)
+  }
+
+  /// Runs `body` while `cond` holds, `increment` is evaluated after each iteration.
+  fn execute_loop(&mut self, cond: &Expr, body: &Stmt, increment: Option<&Expr>) -> Flow<()> {
+    while cond.accept(self)?.is_true() {
+      match self.execute(body) {
+        Ok(()) | Err(Unwind::Continue) => {}
+        Err(Unwind::Break) => break,
+        Err(unwind) => return Err(unwind),
+      }
+      if let Some(increment) = increment {
+        increment.accept(self)?;
       }
     }
-    self.disable_repl_mode();
     Ok(())
   }
 
-  pub fn execute(&mut self, root: &Stmt) {
-    root.accept(self);
+  fn execute_stmts(&mut self, stmts: &[Stmt]) -> Flow<()> {
+    stmts.iter().try_for_each(|stmt| self.execute(stmt))
   }
 
-  pub fn execute_block(&mut self, stmts: &[Stmt]) {
-    self.env.new_enclosed();
-    for stmt in stmts {
-      self.execute(stmt);
-      if self.has_runtime_error {
-        self.env.drop_innermost_scope();
-        return;
+  fn execute_root(&mut self, root: &Stmt) -> Flow<()> {
+    match root {
+      // Only values of top-level expressions are printed.
+      Stmt::Expression { expr, .. } if self.is_in_repl => {
+        println!("{}", self.evaluate(expr)?);
+        Ok(())
       }
+      _ => self.execute(root),
     }
-    self.env.drop_innermost_scope();
   }
 }
 
-#[allow(unused_variables)]
+impl Runtime for Interpreter {
+  fn interpret(&mut self, roots: &[Stmt], is_in_repl: bool) -> Result<(), RuntimeError> {
+    self.is_in_repl = is_in_repl;
+    let result = roots.iter().try_for_each(|root| self.execute_root(root));
+    self.disable_repl_mode();
+    result.map_err(Unwind::into_error)
+  }
+
+  fn define_native(&mut self, native: NativeFunction) {
+    let name = native.name().clone();
+    self.env.define_global(name, Value::Native(Rc::new(native)));
+  }
+}
+
+/// Statements evaluate to `nil`, and `return` unwinds with [Unwind::Return].
+///
+/// Runtime errors unwind with [Unwind::Error], until the top level.
 impl Visitor for Interpreter {
-  type Output = Option<LiteralValue>;
+  type Output = Flow;
 
   fn visit_stmt(&mut self, stmt: &Stmt) -> Self::Output {
     use Stmt::*;
 
     match stmt {
-      Block { stmts } => {
-        self.execute_block(stmts);
-        None // Blocks don't return a value.
-      }
+      Block { stmts, .. } => self.execute_block(stmts)?,
+      Break { .. } => return Err(Unwind::Break),
+      Continue { .. } => return Err(Unwind::Continue),
       Class {
         name,
         superclass,
         methods,
-      } => todo!(),
-      Expression { expr } => {
-        let curr_val = expr.accept(self);
-        if curr_val.is_none() {
-          return self.runtime_error();
-        } else if self.is_in_repl {
-          println!("{}", curr_val.unwrap());
+        ..
+      } => {
+        let superclass = match superclass {
+          Some((super_name, Some(lookup))) => {
+            let Value::Class(class) = lookup.accept(self)? else {
+              return self.error(ErrorKind::SuperclassNotClass, super_name.1);
+            };
+            Some(class)
+          }
+          _ => None,
+        };
+        self.env.define(name.0.to_owned(), Value::nil());
+
+        // Methods of a subclass could access `super` from their closure.
+        if let Some(superclass) = &superclass {
+          self.env.new_enclosed();
+          self
+            .env
+            .define("super".into(), Value::Class(superclass.clone()));
         }
-        None // Don't return anything for script mode.
+        let methods = methods
+          .iter()
+          .map(|(method_name, params, body)| {
+            let method = LoxFunction::new(
+              method_name.to_owned(),
+              params.to_owned(),
+              body.to_owned(),
+              self.env.clone(),
+              method_name.0.as_ref() == "init",
+            );
+            (method_name.0.to_owned(), Value::Function(Rc::new(method)))
+          })
+          .collect::<HashMap<_, _>>();
+        if superclass.is_some() {
+          self.env.drop_innermost_scope();
+        }
+
+        let class = LoxClass::new(name.to_owned(), superclass, methods);
+        self
+          .env
+          .assign_at(Some(0), name.0.to_owned(), Value::Class(Rc::new(class)));
+      }
+      Expression { expr, .. } => {
+        expr.accept(self)?;
+      }
+      Function {
+        name, params, body, ..
+      } => {
+        // Capture the current environment as the closure.
+        let function = LoxFunction::new(
+          name.to_owned(),
+          params.to_owned(),
+          body.to_owned(),
+          self.env.clone(),
+          false,
+        );
+        self
+          .env
+          .define(name.0.to_owned(), Value::Function(Rc::new(function)));
       }
-      Function { name, params, body } => todo!(),
       If {
         cond,
         then_branch,
         else_branch,
+        ..
       } => {
-        let cond = cond.accept(self)?;
-        if cond.is_true() {
-          self.execute(then_branch);
+        if cond.accept(self)?.is_true() {
+          self.execute(then_branch)?;
         } else if let Some(else_branch) = else_branch {
-          self.execute(else_branch);
+          self.execute(else_branch)?;
         }
-        None // If statements don't return a value.
       }
-      Print { expr } => {
-        let Some(val) = expr.accept(self) else {
-          return self.runtime_error();
-        };
+      Print { expr, .. } => {
+        let val = expr.accept(self)?;
         println!("{}", val);
-        None // Print statements don't return a value.
-      }
-      Return { keyword: _, value } => todo!(),
-      Variable { name, initializer } => {
-        let mut val = LiteralValue::Nil;
-        if let Some(expr) = initializer {
-          match expr.accept(self) {
-            Some(v) => val = v,
-            None => return self.runtime_error(),
-          }
-        }
+      }
+      Return {
+        keyword: _, value, ..
+      } => {
+        let val = match value {
+          Some(expr) => expr.accept(self)?,
+          None => Value::nil(),
+        };
+        return Err(Unwind::Return(val)); // Start unwinding.
+      }
+      Variable {
+        name, initializer, ..
+      } => {
+        let val = match initializer {
+          Some(expr) => expr.accept(self)?,
+          None => Value::nil(),
+        };
         // Define the var
         self.env.define(name.0.to_owned(), val);
-        None // Variable declarations don't return a value.
       }
-      While { cond, body } => {
-        while cond.accept(self)?.is_true() {
-          self.execute(body);
-          if self.has_runtime_error {
-            return None;
-          }
+      While { cond, body, .. } => self.execute_loop(cond, body, None)?,
+      For {
+        initializer,
+        cond,
+        increment,
+        body,
+        ..
+      } => {
+        self.env.new_enclosed();
+        let result = match initializer {
+          Some(initializer) => self.execute(initializer),
+          None => Ok(()),
         }
-        None // While loops don't return a value.
+        .and_then(|_| self.execute_loop(cond, body, increment.as_ref()));
+        self.env.drop_innermost_scope();
+        result?;
       }
     }
+    Ok(Value::nil())
   }
 
   fn visit_expr(&mut self, expr: &Expr) -> Self::Output {
@@ -151,98 +373,237 @@ impl Visitor for Interpreter {
     use Operator::*;
 
     match expr {
-      Assign { name, val } => {
+      Assign {
+        name, val, depth, ..
+      } => {
         let val = val.accept(self)?;
-        let var_name = name.0.to_owned();
-        let line = name.1;
-        if self.env.assign(var_name.to_owned(), val.clone()).is_none() {
-          return report(line, &format!("Undefined variable: '{}'.", var_name));
+        if self
+          .env
+          .assign_at(depth.get(), name.0.to_owned(), val.clone())
+          .is_none()
+        {
+          return self.error(ErrorKind::UndefinedVariable(name.0.to_string()), name.1);
         }
-        Some(val) // To enable something like `var a = 1; print a = 2;`
+        Ok(val) // To enable something like `var a = 1; print a = 2;`
       }
-      Binary { left, op, right } => {
+      Binary {
+        left, op, right, ..
+      } => {
         let left = left.accept(self)?;
         let right = right.accept(self)?;
-        match op.0 {
-          Plus => match left.add(&right) {
-            Ok(r) => r.into(),
-            Err(e) => report(op.1, &e),
-          },
-          Minus => match left.sub(&right) {
-            Ok(r) => r.into(),
-            Err(e) => report(op.1, &e),
-          },
-          Multiply => match left.mul(&right) {
-            Ok(r) => r.into(),
-            Err(e) => report(op.1, &e),
-          },
-          Divide => match left.div(&right) {
-            Ok(r) => r.into(),
-            Err(e) => report(op.1, &e),
-          },
-          Greater => match left.check_both_numeric(&right) {
-            Ok((l, r)) => Some(l.gt(&r).into()),
-            Err(e) => report(op.1, &e),
-          },
-          GreaterEqual => match left.check_both_numeric(&right) {
-            Ok((l, r)) => Some(l.ge(&r).into()),
-            Err(e) => report(op.1, &e),
-          },
-          Less => match left.check_both_numeric(&right) {
-            Ok((l, r)) => Some(l.lt(&r).into()),
-            Err(e) => report(op.1, &e),
-          },
-          LessEqual => match left.check_both_numeric(&right) {
-            Ok((l, r)) => Some(l.le(&r).into()),
-            Err(e) => report(op.1, &e),
-          },
-          Equal => Some(left.eq(&right).into()),
-          NotEqual => Some(left.ne(&right).into()),
-          _ => report(op.1, &format!("Invalid binary operator: {}", op.0)),
+        self.binary_op(left, op, right)
+      }
+      Call { callee, args, .. } => {
+        let span = expr.pos();
+        let callee = callee.accept(self)?;
+        let mut arguments = Vec::with_capacity(args.len());
+        for arg in args {
+          arguments.push(arg.accept(self)?);
+        }
+
+        let function: &dyn Callable = match &callee {
+          Value::Function(function) => function.as_ref(),
+          Value::Class(class) => class,
+          Value::Native(native) => native.as_ref(),
+          _ => return self.error(ErrorKind::NotCallable, span),
+        };
+        if arguments.len() != function.arity() {
+          let kind = ErrorKind::ArityMismatch {
+            expected: function.arity(),
+            found: arguments.len(),
+          };
+          return self.error(kind, span);
+        }
+        function.call(self, arguments, span)
+      }
+      Get { obj, name, .. } => {
+        let Value::Instance(instance) = obj.accept(self)? else {
+          return self.error(ErrorKind::NoProperties, name.1);
+        };
+        match get_property(&instance, &name.0) {
+          Some(v) => Ok(v),
+          None => self.error(ErrorKind::UndefinedProperty(name.0.to_string()), name.1),
+        }
+      }
+      Grouping { expr, .. } => expr.accept(self),
+      // Every part is stringified as it's printed.
+      Interpolation { parts, .. } => {
+        let mut string = String::new();
+        for part in parts {
+          string += &part.accept(self)?.to_string();
+        }
+        Ok(string.as_str().into())
+      }
+      Index { obj, index, .. } => {
+        let obj = obj.accept(self)?;
+        let i = index.accept(self)?;
+        obj
+          .get_index(&i)
+          .or_else(|kind| self.error(kind, index.span()))
+      }
+      // Like a function declaration, the current environment is captured.
+      Lambda { params, body, span } => {
+        let function = LoxFunction::new(
+          (LAMBDA_NAME, *span).into(),
+          params.to_owned(),
+          body.to_owned(),
+          self.env.clone(),
+          false,
+        );
+        Ok(Value::Function(Rc::new(function)))
+      }
+      List { elements, .. } => {
+        let mut values = Vec::with_capacity(elements.len());
+        for element in elements {
+          values.push(element.accept(self)?);
         }
+        Ok(Value::List(Rc::new(LoxList::new(values))))
       }
-      Call { callee, args } => todo!(),
-      Get { obj, name } => todo!(),
-      Grouping { expr } => expr.accept(self),
-      Literal { val } => Some(val.0.to_owned()),
-      Logical { left, op, right } => {
+      Literal { val, .. } => Ok(val.0.to_owned().into()),
+      Map { entries, span } => {
+        let map = LoxMap::default();
+        for (key, val) in entries {
+          let key = key.accept(self)?;
+          let val = val.accept(self)?;
+          match key.as_key() {
+            Ok(key) => map.insert(key, val),
+            Err(kind) => return self.error(kind, *span),
+          }
+        }
+        Ok(Value::Map(Rc::new(map)))
+      }
+      Logical {
+        left, op, right, ..
+      } => {
         let left = left.accept(self)?;
         if let Operator::Or = op.0 {
           if left.is_true() {
-            return left.into();
+            return Ok(left);
           }
         } else {
           if !left.is_true() {
-            return left.into();
+            return Ok(left);
           }
         }
         right.accept(self)
       }
-      Set { obj, name, val } => todo!(),
-      Super { keyword: _, method } => todo!(),
-      This { keyword: _ } => todo!(),
-      Unary { op, right } => {
+      Set { obj, name, val, .. } => {
+        let obj = obj.accept(self)?;
+        let val = val.accept(self)?;
+        let Value::Instance(instance) = obj else {
+          return self.error(ErrorKind::NoFields, name.1);
+        };
+        instance.set(name.0.to_owned(), val.clone());
+        Ok(val)
+      }
+      SetIndex {
+        obj, index, val, ..
+      } => {
+        let obj = obj.accept(self)?;
+        let i = index.accept(self)?;
+        let val = val.accept(self)?;
+        match obj.set_index(&i, val.clone()) {
+          Ok(()) => Ok(val),
+          Err(kind) => self.error(kind, index.span()),
+        }
+      }
+      Super {
+        keyword,
+        method,
+        depth,
+        ..
+      } => {
+        // `this` is always bound right inside the scope of `super`.
+        let superclass = self.env.get_at(depth.get(), "super");
+        let this = depth
+          .get()
+          .and_then(|depth| self.env.get_at(Some(depth - 1), "this"));
+        let (Some(Value::Class(superclass)), Some(this)) = (superclass, this) else {
+          return self.error(ErrorKind::InvalidSuper, keyword.1);
+        };
+        match bind_method(&superclass, &method.0, this) {
+          Some(m) => Ok(m),
+          None => self.error(ErrorKind::UndefinedProperty(method.0.to_string()), method.1),
+        }
+      }
+      This { keyword, depth, .. } => match self.env.get_at(depth.get(), "this") {
+        Some(v) => Ok(v),
+        None => self.error(ErrorKind::InvalidThis, keyword.1),
+      },
+      Update {
+        target,
+        op,
+        val,
+        is_postfix,
+        ..
+      } => {
+        let val = val.as_deref();
+        let (old, new) = match target.as_ref() {
+          Var { name, depth, .. } => {
+            let old = target.accept(self)?;
+            let new = self.update_op(old.clone(), op, val)?;
+            self
+              .env
+              .assign_at(depth.get(), name.0.to_owned(), new.clone());
+            (old, new)
+          }
+          Get { obj, name, .. } => {
+            let Value::Instance(instance) = obj.accept(self)? else {
+              return self.error(ErrorKind::NoProperties, name.1);
+            };
+            let Some(old) = get_property(&instance, &name.0) else {
+              return self.error(ErrorKind::UndefinedProperty(name.0.to_string()), name.1);
+            };
+            let new = self.update_op(old.clone(), op, val)?;
+            instance.set(name.0.to_owned(), new.clone());
+            (old, new)
+          }
+          Index { obj, index, .. } => {
+            let obj = obj.accept(self)?;
+            let i = index.accept(self)?;
+            let old = obj
+              .get_index(&i)
+              .or_else(|kind| self.error(kind, index.span()))?;
+            let new = self.update_op(old.clone(), op, val)?;
+            if let Err(kind) = obj.set_index(&i, new.clone()) {
+              return self.error(kind, index.span());
+            }
+            (old, new)
+          }
+          _ => unreachable!("invalid update target"),
+        };
+        Ok(if *is_postfix { old } else { new })
+      }
+      Ternary {
+        cond,
+        then_branch,
+        else_branch,
+        ..
+      } => {
+        if cond.accept(self)?.is_true() {
+          then_branch.accept(self)
+        } else {
+          else_branch.accept(self)
+        }
+      }
+      Unary { op, right, .. } => {
         let right = right.accept(self)?;
         match op.0 {
-          Minus => match right.neg() {
-            Ok(r) => r.into(),
-            Err(e) => report(op.1, &e),
+          Minus => match &right {
+            Value::Literal(l) => match l.neg() {
+              Ok(r) => Ok(r.into()),
+              Err(e) => self.error(ErrorKind::TypeMismatch(e), op.1),
+            },
+            _ => self.error(ErrorKind::TypeMismatch(OperandError::ExpectNumber), op.1),
           },
-          Not => match right.not() {
-            Ok(r) => r.into(),
-            Err(e) => report(op.1, &e),
-          },
-          _ => report(op.1, &format!("Invalid unary operator: {}", op.0)),
-        }
-      }
-      Var { name } => {
-        let var_name = name.0.to_owned();
-        let line = name.1;
-        match self.env.get(&var_name) {
-          Some(v) => Some(v.value().clone()),
-          None => report(line, &format!("Undefined variable: '{}'.", var_name)),
+          Not => Ok((!right.is_true()).into()),
+          _ => unreachable!("invalid unary operator: {}", op.0),
         }
       }
+      Var { name, depth, .. } => match self.env.get_at(depth.get(), &name.0) {
+        Some(v) => Ok(v),
+        None => self.error(ErrorKind::UndefinedVariable(name.0.to_string()), name.1),
+      },
     }
   }
 }
@@ -0,0 +1,109 @@
+//! A chunk of bytecode, with its constant pool and span table.
+
+use super::object::Function;
+use ember_lox_ast::ast::prelude::*;
+use ember_lox_tokenizer::Span;
+use std::rc::Rc;
+
+/// Instructions of the virtual machine.
+///
+/// Operands are stored inline. Unless noted, `usize` operands are indices into
+/// the constant pool, or jump targets (absolute indices into the code).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
+  /// Pushes a literal constant.
+  Constant(usize),
+  Nil,
+  True,
+  False,
+  Pop,
+  /// Operand is the distance from the top of the value to push again, e.g. `0` for the top.
+  Copy(usize),
+  /// Operand is how many values the top one is moved under, e.g. `2` turns `a b c` into `c a b`.
+  Sink(usize),
+
+  /// Operand is the stack slot (relative to the current frame).
+  GetLocal(usize),
+  /// Operand is the stack slot (relative to the current frame).
+  SetLocal(usize),
+  GetGlobal(usize),
+  DefineGlobal(usize),
+  SetGlobal(usize),
+  /// Operand is the index into the upvalues of the current closure.
+  GetUpvalue(usize),
+  /// Operand is the index into the upvalues of the current closure.
+  SetUpvalue(usize),
+  GetProperty(usize),
+  SetProperty(usize),
+  GetSuper(usize),
+  /// Pops the list (or map) and the index, pushes the element.
+  GetIndex,
+  /// Pops the list (or map), the index and the value, pushes the value.
+  SetIndex,
+
+  Equal,
+  Greater,
+  GreaterEqual,
+  Less,
+  LessEqual,
+  Add,
+  Subtract,
+  Multiply,
+  Divide,
+  Modulo,
+  Power,
+  Not,
+  Negate,
+  /// Operand is the number of values on the stack, which are stringified and concatenated.
+  Interpolate(usize),
+
+  Print,
+  Jump(usize),
+  /// Jumps if the top of the stack is falsey, without popping it.
+  JumpIfFalse(usize),
+
+  /// Operand is the number of arguments.
+  Call(usize),
+  /// Operand is the index of a function constant.
+  Closure(usize),
+  CloseUpvalue,
+  Return,
+
+  /// Operand is the number of elements on the stack.
+  List(usize),
+  /// Operand is the number of entries, whose keys and values are on the stack in turn.
+  Map(usize),
+
+  Class(usize),
+  Inherit,
+  Method(usize),
+}
+
+#[derive(Debug, Clone)]
+pub enum Constant {
+  Literal(LiteralValue),
+  Function(Rc<Function>),
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+  pub code: Vec<OpCode>,
+  pub constants: Vec<Constant>,
+  /// `spans[i]` is where `code[i]` comes from in the source.
+  pub spans: Vec<Span>,
+}
+
+impl Chunk {
+  /// Appends the instruction, returns its index.
+  pub fn write(&mut self, op: OpCode, span: Span) -> usize {
+    self.code.push(op);
+    self.spans.push(span);
+    self.code.len() - 1
+  }
+
+  /// Appends the constant, returns its index.
+  pub fn add_constant(&mut self, constant: Constant) -> usize {
+    self.constants.push(constant);
+    self.constants.len() - 1
+  }
+}
@@ -0,0 +1,671 @@
+//! A single-pass compiler from `AST` to bytecode.
+//!
+//! The `AST` is expected to have been checked by [Resolver](crate::resolver::Resolver),
+//! so that no static error will be met here.
+
+use super::{
+  chunk::{Constant, OpCode},
+  object::{Function, UpvalueDesc},
+};
+use crate::LAMBDA_NAME;
+use ember_lox_ast::{
+  ast::prelude::*,
+  visit::{Visitor, VisitorAcceptor},
+  STR,
+};
+use ember_lox_tokenizer::Span;
+use std::{rc::Rc, sync::Arc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionKind {
+  Script,
+  Function,
+  Method,
+  Initializer,
+}
+
+struct Local {
+  name: Arc<str>,
+  depth: usize,
+  /// Whether it's captured by a closure (so it should be moved off the stack).
+  is_captured: bool,
+}
+
+/// An enclosing `while` or `for`, whose jumps are patched once its body is compiled.
+struct Loop {
+  /// Scope depth of the loop itself, deeper locals are left by `break` and `continue`.
+  depth: usize,
+  breaks: Vec<usize>,
+  continues: Vec<usize>,
+}
+
+/// Compilation state of one function.
+struct FunctionState {
+  function: Function,
+  kind: FunctionKind,
+  locals: Vec<Local>,
+  scope_depth: usize,
+  /// The innermost loop is at the back.
+  loops: Vec<Loop>,
+}
+
+impl FunctionState {
+  fn new(kind: FunctionKind, name: Arc<str>) -> Self {
+    // Slot zero holds the callee itself, or `this` for methods.
+    let slot_zero = match kind {
+      FunctionKind::Method | FunctionKind::Initializer => "this",
+      _ => "",
+    };
+    Self {
+      function: Function {
+        name,
+        ..Default::default()
+      },
+      kind,
+      locals: vec![Local {
+        name: slot_zero.into(),
+        depth: 0,
+        is_captured: false,
+      }],
+      scope_depth: 0,
+      loops: vec![],
+    }
+  }
+}
+
+pub struct Compiler {
+  /// The innermost function is at the back.
+  states: Vec<FunctionState>,
+  /// Line of the most recently emitted instruction.
+  span: Span,
+}
+
+impl Compiler {
+  /// Compiles `roots` into the top-level script function.
+  pub fn compile(roots: &[Stmt], is_in_repl: bool) -> Function {
+    let mut compiler = Self {
+      states: vec![FunctionState::new(FunctionKind::Script, "".into())],
+      span: Span::default(),
+    };
+    for root in roots {
+      match root {
+        // Only values of top-level expressions are printed.
+        Stmt::Expression { expr, .. } if is_in_repl => {
+          expr.accept(&mut compiler);
+          compiler.emit_here(OpCode::Print);
+        }
+        _ => root.accept(&mut compiler),
+      }
+    }
+    compiler.end_function()
+  }
+
+  fn state(&mut self) -> &mut FunctionState {
+    self.states.last_mut().unwrap()
+  }
+
+  fn emit(&mut self, op: OpCode, span: Span) -> usize {
+    self.span = span;
+    self.state().function.chunk.write(op, span)
+  }
+
+  /// Emits with the span of the previous instruction.
+  fn emit_here(&mut self, op: OpCode) -> usize {
+    self.emit(op, self.span)
+  }
+
+  fn next_index(&mut self) -> usize {
+    self.state().function.chunk.code.len()
+  }
+
+  /// Points the jump at `jump` to the next instruction.
+  fn patch_jump(&mut self, jump: usize) {
+    let target = self.next_index();
+    let code = &mut self.state().function.chunk.code;
+    code[jump] = match code[jump] {
+      OpCode::Jump(_) => OpCode::Jump(target),
+      OpCode::JumpIfFalse(_) => OpCode::JumpIfFalse(target),
+      op => unreachable!("{:?} is not a jump", op),
+    };
+  }
+
+  fn add_constant(&mut self, constant: Constant) -> usize {
+    self.state().function.chunk.add_constant(constant)
+  }
+
+  fn name_constant(&mut self, name: &str) -> usize {
+    self.add_constant(Constant::Literal(name.into()))
+  }
+
+  fn begin_scope(&mut self) {
+    self.state().scope_depth += 1;
+  }
+
+  fn end_scope(&mut self) {
+    self.state().scope_depth -= 1;
+    let depth = self.state().scope_depth;
+    while let Some(local) = self.state().locals.last() {
+      if local.depth <= depth {
+        break;
+      }
+      let op = if local.is_captured {
+        OpCode::CloseUpvalue
+      } else {
+        OpCode::Pop
+      };
+      self.emit_here(op);
+      self.state().locals.pop();
+    }
+  }
+
+  /// Jumps out of the body of the innermost loop, to be patched as a `break` or `continue`.
+  fn emit_loop_exit(&mut self, is_break: bool, span: Span) {
+    let depth = self.state().loops.last().unwrap().depth;
+    // Locals are discarded but kept declared, for the rest of their scopes.
+    // A local may be captured later, so the upvalues are always closed.
+    let count = self
+      .state()
+      .locals
+      .iter()
+      .rev()
+      .take_while(|local| local.depth > depth)
+      .count();
+    for _ in 0..count {
+      self.emit(OpCode::CloseUpvalue, span);
+    }
+    let jump = self.emit(OpCode::Jump(0), span);
+    let current = self.state().loops.last_mut().unwrap();
+    if is_break {
+      current.breaks.push(jump);
+    } else {
+      current.continues.push(jump);
+    }
+  }
+
+  /// Compiles a loop, `increment` is evaluated after each iteration.
+  fn loop_stmt(&mut self, cond: &Expr, body: &Stmt, increment: Option<&Expr>) {
+    let loop_start = self.next_index();
+    cond.accept(self);
+    let exit_jump = self.emit_here(OpCode::JumpIfFalse(0));
+    self.emit_here(OpCode::Pop);
+
+    let depth = self.state().scope_depth;
+    self.state().loops.push(Loop {
+      depth,
+      breaks: vec![],
+      continues: vec![],
+    });
+    body.accept(self);
+    let Loop {
+      breaks, continues, ..
+    } = self.state().loops.pop().unwrap();
+
+    continues.into_iter().for_each(|jump| self.patch_jump(jump));
+    if let Some(increment) = increment {
+      increment.accept(self);
+      self.emit_here(OpCode::Pop);
+    }
+    self.emit_here(OpCode::Jump(loop_start));
+
+    self.patch_jump(exit_jump);
+    self.emit_here(OpCode::Pop);
+    // The condition is not on the stack after a `break`.
+    breaks.into_iter().for_each(|jump| self.patch_jump(jump));
+  }
+
+  /// Emits a binary `op` (except the logical ones), whose operands are on the stack.
+  fn binary_op(&mut self, op: &PosedOperator) {
+    let span = op.1;
+    match op.0 {
+      Operator::Plus => self.emit(OpCode::Add, span),
+      Operator::Minus => self.emit(OpCode::Subtract, span),
+      Operator::Multiply => self.emit(OpCode::Multiply, span),
+      Operator::Divide => self.emit(OpCode::Divide, span),
+      Operator::Modulo => self.emit(OpCode::Modulo, span),
+      Operator::Power => self.emit(OpCode::Power, span),
+      Operator::Equal => self.emit(OpCode::Equal, span),
+      Operator::NotEqual => {
+        self.emit(OpCode::Equal, span);
+        self.emit(OpCode::Not, span)
+      }
+      Operator::Greater => self.emit(OpCode::Greater, span),
+      Operator::GreaterEqual => self.emit(OpCode::GreaterEqual, span),
+      Operator::Less => self.emit(OpCode::Less, span),
+      Operator::LessEqual => self.emit(OpCode::LessEqual, span),
+      op => unreachable!("invalid binary operator: {}", op),
+    };
+  }
+
+  /// Declares a local variable, whose value is at the top of the stack.
+  fn add_local(&mut self, name: &str) {
+    let depth = self.state().scope_depth;
+    self.state().locals.push(Local {
+      name: name.into(),
+      depth,
+      is_captured: false,
+    });
+  }
+
+  /// Defines the variable with the value at the top of the stack.
+  fn define_variable(&mut self, name: &STR) {
+    if self.state().scope_depth > 0 {
+      self.add_local(&name.0);
+    } else {
+      let idx = self.name_constant(&name.0);
+      self.emit(OpCode::DefineGlobal(idx), name.1);
+    }
+  }
+
+  fn resolve_local(&self, level: usize, name: &str) -> Option<usize> {
+    self.states[level]
+      .locals
+      .iter()
+      .rposition(|local| local.name.as_ref() == name)
+  }
+
+  fn resolve_upvalue(&mut self, level: usize, name: &str) -> Option<usize> {
+    if level == 0 {
+      return None;
+    }
+    let enclosing = level - 1;
+    if let Some(slot) = self.resolve_local(enclosing, name) {
+      self.states[enclosing].locals[slot].is_captured = true;
+      return Some(self.add_upvalue(level, slot, true));
+    }
+    let index = self.resolve_upvalue(enclosing, name)?;
+    Some(self.add_upvalue(level, index, false))
+  }
+
+  fn add_upvalue(&mut self, level: usize, index: usize, is_local: bool) -> usize {
+    let upvalues = &mut self.states[level].function.upvalues;
+    if let Some(existing) = upvalues
+      .iter()
+      .position(|uv| uv.index == index && uv.is_local == is_local)
+    {
+      return existing;
+    }
+    upvalues.push(UpvalueDesc { index, is_local });
+    upvalues.len() - 1
+  }
+
+  fn get_variable(&mut self, name: &str, span: Span) {
+    let level = self.states.len() - 1;
+    let op = if let Some(slot) = self.resolve_local(level, name) {
+      OpCode::GetLocal(slot)
+    } else if let Some(index) = self.resolve_upvalue(level, name) {
+      OpCode::GetUpvalue(index)
+    } else {
+      OpCode::GetGlobal(self.name_constant(name))
+    };
+    self.emit(op, span);
+  }
+
+  fn set_variable(&mut self, name: &str, span: Span) {
+    let level = self.states.len() - 1;
+    let op = if let Some(slot) = self.resolve_local(level, name) {
+      OpCode::SetLocal(slot)
+    } else if let Some(index) = self.resolve_upvalue(level, name) {
+      OpCode::SetUpvalue(index)
+    } else {
+      OpCode::SetGlobal(self.name_constant(name))
+    };
+    self.emit(op, span);
+  }
+
+  /// Emits the implicit return, and finishes the innermost function.
+  fn end_function(&mut self) -> Function {
+    self.emit_return();
+    self.states.pop().unwrap().function
+  }
+
+  fn emit_return(&mut self) {
+    if self.state().kind == FunctionKind::Initializer {
+      self.emit_here(OpCode::GetLocal(0));
+    } else {
+      self.emit_here(OpCode::Nil);
+    }
+    self.emit_here(OpCode::Return);
+  }
+
+  /// Compiles the function, and leaves a closure of it on the stack.
+  fn function(&mut self, name: &STR, params: &[STR], body: &[Stmt], kind: FunctionKind) {
+    self
+      .states
+      .push(FunctionState::new(kind, name.0.to_owned()));
+    self.begin_scope();
+    self.state().function.arity = params.len();
+    for param in params {
+      self.add_local(&param.0);
+    }
+    for stmt in body {
+      stmt.accept(self);
+    }
+    let function = self.end_function();
+
+    let idx = self.add_constant(Constant::Function(Rc::new(function)));
+    self.emit(OpCode::Closure(idx), name.1);
+  }
+}
+
+impl Visitor for Compiler {
+  type Output = ();
+
+  fn visit_stmt(&mut self, stmt: &Stmt) -> Self::Output {
+    use Stmt::*;
+
+    match stmt {
+      Block { stmts, .. } => {
+        self.begin_scope();
+        stmts.iter().for_each(|stmt| stmt.accept(self));
+        self.end_scope();
+      }
+      Class {
+        name,
+        superclass,
+        methods,
+        ..
+      } => {
+        let name_idx = self.name_constant(&name.0);
+        self.emit(OpCode::Class(name_idx), name.1);
+        self.define_variable(name);
+
+        if let Some((super_name, Some(lookup))) = superclass {
+          lookup.accept(self);
+          // Methods of a subclass could capture `super` like a local.
+          self.begin_scope();
+          self.add_local("super");
+          self.get_variable(&name.0, name.1);
+          self.emit(OpCode::Inherit, super_name.1);
+        }
+
+        // Keep the class on the stack while binding methods.
+        self.get_variable(&name.0, name.1);
+        for (method_name, params, body) in methods {
+          let kind = if method_name.0.as_ref() == "init" {
+            FunctionKind::Initializer
+          } else {
+            FunctionKind::Method
+          };
+          self.function(method_name, params, body, kind);
+          let method_idx = self.name_constant(&method_name.0);
+          self.emit(OpCode::Method(method_idx), method_name.1);
+        }
+        self.emit_here(OpCode::Pop);
+
+        if superclass.is_some() {
+          self.end_scope();
+        }
+      }
+      Expression { expr, .. } => {
+        expr.accept(self);
+        self.emit_here(OpCode::Pop);
+      }
+      Function {
+        name, params, body, ..
+      } => {
+        // Declare first, so that a local function could refer to itself.
+        if self.state().scope_depth > 0 {
+          self.add_local(&name.0);
+          self.function(name, params, body, FunctionKind::Function);
+        } else {
+          self.function(name, params, body, FunctionKind::Function);
+          self.define_variable(name);
+        }
+      }
+      If {
+        cond,
+        then_branch,
+        else_branch,
+        ..
+      } => {
+        cond.accept(self);
+        let then_jump = self.emit_here(OpCode::JumpIfFalse(0));
+        self.emit_here(OpCode::Pop);
+        then_branch.accept(self);
+        let else_jump = self.emit_here(OpCode::Jump(0));
+
+        self.patch_jump(then_jump);
+        self.emit_here(OpCode::Pop);
+        if let Some(else_branch) = else_branch {
+          else_branch.accept(self);
+        }
+        self.patch_jump(else_jump);
+      }
+      Print { expr, .. } => {
+        expr.accept(self);
+        self.emit_here(OpCode::Print);
+      }
+      Return { keyword, value, .. } => match value {
+        Some(value) => {
+          value.accept(self);
+          self.emit(OpCode::Return, keyword.1);
+        }
+        None => {
+          self.span = keyword.1;
+          self.emit_return();
+        }
+      },
+      Variable {
+        name, initializer, ..
+      } => {
+        match initializer {
+          Some(initializer) => initializer.accept(self),
+          None => {
+            self.emit(OpCode::Nil, name.1);
+          }
+        }
+        self.define_variable(name);
+      }
+      While { cond, body, .. } => self.loop_stmt(cond, body, None),
+      For {
+        initializer,
+        cond,
+        increment,
+        body,
+        ..
+      } => {
+        self.begin_scope();
+        if let Some(initializer) = initializer {
+          initializer.accept(self);
+        }
+        self.loop_stmt(cond, body, increment.as_ref());
+        self.end_scope();
+      }
+      Break { span } => self.emit_loop_exit(true, *span),
+      Continue { span } => self.emit_loop_exit(false, *span),
+    }
+  }
+
+  fn visit_expr(&mut self, expr: &Expr) -> Self::Output {
+    use Expr::*;
+
+    match expr {
+      Assign { name, val, .. } => {
+        val.accept(self);
+        self.set_variable(&name.0, name.1);
+      }
+      Binary {
+        left, op, right, ..
+      } => {
+        left.accept(self);
+        right.accept(self);
+        self.binary_op(op);
+      }
+      Call { callee, args, .. } => {
+        let span = expr.pos();
+        callee.accept(self);
+        args.iter().for_each(|arg| arg.accept(self));
+        self.emit(OpCode::Call(args.len()), span);
+      }
+      Get { obj, name, .. } => {
+        obj.accept(self);
+        let idx = self.name_constant(&name.0);
+        self.emit(OpCode::GetProperty(idx), name.1);
+      }
+      Grouping { expr, .. } => expr.accept(self),
+      Interpolation { parts, span } => {
+        parts.iter().for_each(|p| p.accept(self));
+        self.emit(OpCode::Interpolate(parts.len()), *span);
+      }
+      Index { obj, index, .. } => {
+        obj.accept(self);
+        index.accept(self);
+        self.emit(OpCode::GetIndex, index.span());
+      }
+      Lambda { params, body, span } => {
+        let name = (LAMBDA_NAME, *span).into();
+        self.function(&name, params, body, FunctionKind::Function);
+      }
+      List { elements, span } => {
+        elements.iter().for_each(|e| e.accept(self));
+        self.emit(OpCode::List(elements.len()), *span);
+      }
+      Map { entries, span } => {
+        entries.iter().for_each(|(key, val)| {
+          key.accept(self);
+          val.accept(self);
+        });
+        self.emit(OpCode::Map(entries.len()), *span);
+      }
+      Literal { val, .. } => {
+        let op = match &val.0 {
+          LiteralValue::Nil => OpCode::Nil,
+          LiteralValue::Bool(true) => OpCode::True,
+          LiteralValue::Bool(false) => OpCode::False,
+          literal => OpCode::Constant(self.add_constant(Constant::Literal(literal.to_owned()))),
+        };
+        self.emit(op, val.1);
+      }
+      Logical {
+        left, op, right, ..
+      } => {
+        left.accept(self);
+        if let Operator::Or = op.0 {
+          // Skip the right operand if the left one is truthy.
+          let else_jump = self.emit(OpCode::JumpIfFalse(0), op.1);
+          let end_jump = self.emit(OpCode::Jump(0), op.1);
+          self.patch_jump(else_jump);
+          self.emit(OpCode::Pop, op.1);
+          right.accept(self);
+          self.patch_jump(end_jump);
+        } else {
+          let end_jump = self.emit(OpCode::JumpIfFalse(0), op.1);
+          self.emit(OpCode::Pop, op.1);
+          right.accept(self);
+          self.patch_jump(end_jump);
+        }
+      }
+      Set { obj, name, val, .. } => {
+        obj.accept(self);
+        val.accept(self);
+        let idx = self.name_constant(&name.0);
+        self.emit(OpCode::SetProperty(idx), name.1);
+      }
+      SetIndex {
+        obj, index, val, ..
+      } => {
+        obj.accept(self);
+        index.accept(self);
+        val.accept(self);
+        self.emit(OpCode::SetIndex, index.span());
+      }
+      Super {
+        keyword, method, ..
+      } => {
+        self.get_variable("this", keyword.1);
+        self.get_variable("super", keyword.1);
+        let idx = self.name_constant(&method.0);
+        self.emit(OpCode::GetSuper(idx), method.1);
+      }
+      Ternary {
+        cond,
+        then_branch,
+        else_branch,
+        ..
+      } => {
+        cond.accept(self);
+        let else_jump = self.emit_here(OpCode::JumpIfFalse(0));
+        self.emit_here(OpCode::Pop);
+        then_branch.accept(self);
+        let end_jump = self.emit_here(OpCode::Jump(0));
+        self.patch_jump(else_jump);
+        self.emit_here(OpCode::Pop);
+        else_branch.accept(self);
+        self.patch_jump(end_jump);
+      }
+      This { keyword, .. } => self.get_variable("this", keyword.1),
+      Unary { op, right, .. } => {
+        right.accept(self);
+        match op.0 {
+          Operator::Minus => self.emit(OpCode::Negate, op.1),
+          Operator::Not => self.emit(OpCode::Not, op.1),
+          op => unreachable!("invalid unary operator: {}", op),
+        };
+      }
+      Update {
+        target,
+        op,
+        val,
+        is_postfix,
+        ..
+      } => {
+        // Operands of the target are read once, and kept on the stack for the write.
+        let operands = match target.as_ref() {
+          Var { name, .. } => {
+            self.get_variable(&name.0, name.1);
+            0
+          }
+          Get { obj, name, .. } => {
+            obj.accept(self);
+            self.emit(OpCode::Copy(0), name.1);
+            let idx = self.name_constant(&name.0);
+            self.emit(OpCode::GetProperty(idx), name.1);
+            1
+          }
+          Index { obj, index, .. } => {
+            obj.accept(self);
+            index.accept(self);
+            self.emit(OpCode::Copy(1), index.span());
+            self.emit(OpCode::Copy(1), index.span());
+            self.emit(OpCode::GetIndex, index.span());
+            2
+          }
+          _ => unreachable!("invalid update target"),
+        };
+        // The old value is the result, so a copy of it is left under the operands.
+        if *is_postfix {
+          self.emit(OpCode::Copy(0), op.1);
+          if operands > 0 {
+            self.emit(OpCode::Sink(operands + 1), op.1);
+          }
+        }
+
+        match val {
+          Some(val) => val.accept(self),
+          // `++` and `--`
+          None => {
+            let idx = self.add_constant(Constant::Literal(1.0.into()));
+            self.emit(OpCode::Constant(idx), op.1);
+          }
+        }
+        self.binary_op(op);
+
+        match target.as_ref() {
+          Var { name, .. } => self.set_variable(&name.0, name.1),
+          Get { name, .. } => {
+            let idx = self.name_constant(&name.0);
+            self.emit(OpCode::SetProperty(idx), name.1);
+          }
+          Index { index, .. } => {
+            self.emit(OpCode::SetIndex, index.span());
+          }
+          _ => unreachable!("invalid update target"),
+        }
+        if *is_postfix {
+          self.emit(OpCode::Pop, op.1);
+        }
+      }
+      Var { name, .. } => self.get_variable(&name.0, name.1),
+    }
+  }
+}
@@ -1 +1,11 @@
 //! A high-efficiency `bytecode` interpreter for the `Ember-lox` language.
+//!
+//! The `AST` is compiled into a [Chunk](chunk::Chunk) of instructions in a single pass,
+//! which is then executed by a stack-based [Vm].
+
+pub mod chunk;
+pub mod compiler;
+pub mod object;
+pub mod vm;
+
+pub use vm::Vm;
@@ -0,0 +1,46 @@
+//! Objects only used by the `bytecode` virtual machine.
+
+use super::chunk::Chunk;
+use crate::value::Value;
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+/// A compiled function.
+#[derive(Debug, Default)]
+pub struct Function {
+  /// Empty for the top-level script.
+  pub name: Arc<str>,
+  pub arity: usize,
+  pub chunk: Chunk,
+  /// How to capture each upvalue while creating a closure of the function.
+  pub upvalues: Vec<UpvalueDesc>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UpvalueDesc {
+  /// Stack slot (if `is_local`) or upvalue index of the enclosing function.
+  pub index: usize,
+  /// Whether it captures a local variable of the enclosing function.
+  pub is_local: bool,
+}
+
+/// A captured variable.
+#[derive(Debug)]
+pub enum Upvalue {
+  /// Still lives on the stack (absolute slot).
+  Open(usize),
+  /// Has been moved off the stack.
+  Closed(Value),
+}
+
+#[derive(Debug)]
+pub struct Closure {
+  pub function: Rc<Function>,
+  pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+/// A method together with the instance it's accessed from.
+#[derive(Debug)]
+pub struct BoundMethod {
+  pub receiver: Value,
+  pub method: Rc<Closure>,
+}
@@ -0,0 +1,505 @@
+//! A stack-based virtual machine.
+
+use super::{
+  chunk::{Constant, OpCode},
+  compiler::Compiler,
+  object::{BoundMethod, Closure, Upvalue},
+};
+use crate::{
+  error::{ErrorKind, RuntimeError, TraceFrame},
+  native::{self, NativeFunction},
+  object::{LoxClass, LoxInstance, LoxList, LoxMap},
+  runtime::{Runtime, FRAMES_MAX},
+  value::Value,
+};
+use ember_lox_ast::ast::prelude::*;
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc};
+
+struct CallFrame {
+  closure: Rc<Closure>,
+  /// Index of the next instruction.
+  ip: usize,
+  /// Stack index of the frame's slot zero.
+  slots: usize,
+}
+
+pub struct Vm {
+  stack: Vec<Value>,
+  frames: Vec<CallFrame>,
+  globals: HashMap<Arc<str>, Value>,
+  /// Upvalues still pointing to the stack.
+  open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+/// Comes with [native::defaults].
+impl Default for Vm {
+  fn default() -> Self {
+    let mut vm = Self {
+      stack: vec![],
+      frames: vec![],
+      globals: HashMap::new(),
+      open_upvalues: vec![],
+    };
+    native::defaults()
+      .into_iter()
+      .for_each(|native| vm.define_native(native));
+    vm
+  }
+}
+
+/// Boxed, to keep the hot path small.
+type RunResult<T = ()> = Result<T, Box<RuntimeError>>;
+
+impl Runtime for Vm {
+  /// Compiles `roots` into a script function and runs it.
+  fn interpret(&mut self, roots: &[Stmt], is_in_repl: bool) -> Result<(), RuntimeError> {
+    let function = Compiler::compile(roots, is_in_repl);
+    let closure = Rc::new(Closure {
+      function: function.into(),
+      upvalues: vec![],
+    });
+    self.stack.push(Value::Closure(closure.clone()));
+
+    let result = self.call(closure, 0).and_then(|_| self.run());
+    if result.is_err() {
+      self.reset_stack();
+    }
+    result.map_err(|e| *e)
+  }
+
+  fn define_native(&mut self, native: NativeFunction) {
+    let name = native.name().clone();
+    self.globals.insert(name, Value::Native(Rc::new(native)));
+  }
+}
+
+impl Vm {
+  fn reset_stack(&mut self) {
+    self.stack.clear();
+    self.frames.clear();
+    self.open_upvalues.clear();
+  }
+
+  fn frame(&self) -> &CallFrame {
+    self.frames.last().unwrap()
+  }
+
+  /// Raises an error at the current instruction, which records the active calls.
+  fn runtime_error<T>(&mut self, kind: ErrorKind) -> RunResult<T> {
+    let trace = self
+      .frames
+      .iter()
+      .rev()
+      .map(|frame| {
+        let function = &frame.closure.function;
+        TraceFrame {
+          function: (!function.name.is_empty()).then(|| function.name.to_string()),
+          span: function.chunk.spans[frame.ip - 1],
+        }
+      })
+      .collect::<Vec<_>>();
+    let span = trace[0].span;
+    Err(RuntimeError::new(kind, span, trace).into())
+  }
+
+  fn push(&mut self, value: Value) {
+    self.stack.push(value);
+  }
+
+  fn pop(&mut self) -> Value {
+    self.stack.pop().expect("stack underflow")
+  }
+
+  fn peek(&self, distance: usize) -> &Value {
+    &self.stack[self.stack.len() - 1 - distance]
+  }
+
+  fn read_literal(&self, idx: usize) -> LiteralValue {
+    match &self.frame().closure.function.chunk.constants[idx] {
+      Constant::Literal(literal) => literal.to_owned(),
+      Constant::Function(_) => unreachable!("expect a literal constant"),
+    }
+  }
+
+  fn read_name(&self, idx: usize) -> Arc<str> {
+    match self.read_literal(idx) {
+      LiteralValue::String(name) => name,
+      _ => unreachable!("expect a name constant"),
+    }
+  }
+
+  fn run(&mut self) -> RunResult {
+    loop {
+      let frame = self.frames.last_mut().unwrap();
+      let op = frame.closure.function.chunk.code[frame.ip];
+      frame.ip += 1;
+
+      match op {
+        OpCode::Constant(idx) => {
+          let literal = self.read_literal(idx);
+          self.push(literal.into());
+        }
+        OpCode::Nil => self.push(Value::nil()),
+        OpCode::True => self.push(true.into()),
+        OpCode::False => self.push(false.into()),
+        OpCode::Pop => {
+          self.pop();
+        }
+        OpCode::Copy(distance) => self.push(self.peek(distance).clone()),
+        OpCode::Sink(count) => {
+          let value = self.pop();
+          self.stack.insert(self.stack.len() - count, value);
+        }
+
+        OpCode::GetLocal(slot) => {
+          let value = self.stack[self.frame().slots + slot].clone();
+          self.push(value);
+        }
+        OpCode::SetLocal(slot) => {
+          let idx = self.frame().slots + slot;
+          self.stack[idx] = self.peek(0).clone();
+        }
+        OpCode::GetGlobal(idx) => {
+          let name = self.read_name(idx);
+          match self.globals.get(&name) {
+            Some(value) => self.push(value.clone()),
+            None => return self.runtime_error(ErrorKind::UndefinedVariable(name.to_string())),
+          }
+        }
+        OpCode::DefineGlobal(idx) => {
+          let name = self.read_name(idx);
+          let value = self.pop();
+          self.globals.insert(name, value);
+        }
+        OpCode::SetGlobal(idx) => {
+          let name = self.read_name(idx);
+          if !self.globals.contains_key(&name) {
+            return self.runtime_error(ErrorKind::UndefinedVariable(name.to_string()));
+          }
+          let value = self.peek(0).clone();
+          self.globals.insert(name, value);
+        }
+        OpCode::GetUpvalue(idx) => {
+          let upvalue = self.frame().closure.upvalues[idx].clone();
+          let value = match &*upvalue.borrow() {
+            Upvalue::Open(slot) => self.stack[*slot].clone(),
+            Upvalue::Closed(value) => value.clone(),
+          };
+          self.push(value);
+        }
+        OpCode::SetUpvalue(idx) => {
+          let upvalue = self.frame().closure.upvalues[idx].clone();
+          let value = self.peek(0).clone();
+          let mut upvalue = upvalue.borrow_mut();
+          match &mut *upvalue {
+            Upvalue::Open(slot) => self.stack[*slot] = value,
+            Upvalue::Closed(closed) => *closed = value,
+          }
+        }
+        OpCode::GetProperty(idx) => {
+          let Value::Instance(instance) = self.peek(0).clone() else {
+            return self.runtime_error(ErrorKind::NoProperties);
+          };
+          let name = self.read_name(idx);
+          // Fields shadow methods.
+          let value = match instance.field(&name) {
+            Some(field) => field,
+            None => self.bind_method(instance.class(), &name, Value::Instance(instance.clone()))?,
+          };
+          self.pop();
+          self.push(value);
+        }
+        OpCode::SetProperty(idx) => {
+          let Value::Instance(instance) = self.peek(1).clone() else {
+            return self.runtime_error(ErrorKind::NoFields);
+          };
+          let name = self.read_name(idx);
+          let value = self.pop();
+          instance.set(name, value.clone());
+          self.pop();
+          self.push(value);
+        }
+        OpCode::GetSuper(idx) => {
+          let name = self.read_name(idx);
+          let Value::Class(superclass) = self.pop() else {
+            unreachable!("`super` should be a class");
+          };
+          let this = self.pop();
+          let method = self.bind_method(&superclass, &name, this)?;
+          self.push(method);
+        }
+        OpCode::GetIndex => {
+          let element = match self.peek(1).get_index(self.peek(0)) {
+            Ok(element) => element,
+            Err(kind) => return self.runtime_error(kind),
+          };
+          self.pop();
+          self.pop();
+          self.push(element);
+        }
+        OpCode::SetIndex => {
+          let value = self.peek(0).clone();
+          if let Err(kind) = self.peek(2).set_index(self.peek(1), value.clone()) {
+            return self.runtime_error(kind);
+          }
+          self.stack.truncate(self.stack.len() - 3);
+          self.push(value);
+        }
+
+        OpCode::Equal => {
+          let b = self.pop();
+          let a = self.pop();
+          self.push((a == b).into());
+        }
+        OpCode::Greater => self.comparison_op(|a, b| a > b)?,
+        OpCode::GreaterEqual => self.comparison_op(|a, b| a >= b)?,
+        OpCode::Less => self.comparison_op(|a, b| a < b)?,
+        OpCode::LessEqual => self.comparison_op(|a, b| a <= b)?,
+        OpCode::Add => self.binary_op(|a, b| a + b)?,
+        OpCode::Subtract => self.binary_op(|a, b| a - b)?,
+        OpCode::Multiply => self.binary_op(|a, b| a * b)?,
+        OpCode::Divide => self.binary_op(|a, b| a / b)?,
+        OpCode::Modulo => self.binary_op(|a, b| a % b)?,
+        OpCode::Power => self.binary_op(LiteralValue::pow)?,
+        OpCode::Not => {
+          let value = self.pop();
+          self.push((!value.is_true()).into());
+        }
+        OpCode::Negate => {
+          let result = match self.peek(0) {
+            Value::Literal(literal) => -literal,
+            _ => Err(OperandError::ExpectNumber),
+          };
+          match result {
+            Ok(negated) => {
+              self.pop();
+              self.push(negated.into());
+            }
+            Err(e) => return self.runtime_error(ErrorKind::TypeMismatch(e)),
+          }
+        }
+        OpCode::Interpolate(count) => {
+          let parts = self.stack.split_off(self.stack.len() - count);
+          let string = parts.iter().map(ToString::to_string).collect::<String>();
+          self.push(string.as_str().into());
+        }
+
+        OpCode::Print => {
+          let value = self.pop();
+          println!("{}", value);
+        }
+        OpCode::Jump(target) => self.frames.last_mut().unwrap().ip = target,
+        OpCode::JumpIfFalse(target) => {
+          if !self.peek(0).is_true() {
+            self.frames.last_mut().unwrap().ip = target;
+          }
+        }
+
+        OpCode::Call(arg_count) => self.call_value(arg_count)?,
+        OpCode::Closure(idx) => {
+          let frame = self.frame();
+          let Constant::Function(function) = &frame.closure.function.chunk.constants[idx] else {
+            unreachable!("expect a function constant");
+          };
+          let function = function.clone();
+          let (slots, enclosing) = (frame.slots, frame.closure.clone());
+          let upvalues = function
+            .upvalues
+            .iter()
+            .map(|desc| {
+              if desc.is_local {
+                self.capture_upvalue(slots + desc.index)
+              } else {
+                enclosing.upvalues[desc.index].clone()
+              }
+            })
+            .collect();
+          self.push(Value::Closure(Rc::new(Closure { function, upvalues })));
+        }
+        OpCode::CloseUpvalue => {
+          self.close_upvalues(self.stack.len() - 1);
+          self.pop();
+        }
+        OpCode::Return => {
+          let result = self.pop();
+          let frame = self.frames.pop().unwrap();
+          self.close_upvalues(frame.slots);
+          self.stack.truncate(frame.slots);
+          if self.frames.is_empty() {
+            return Ok(());
+          }
+          self.push(result);
+        }
+
+        OpCode::List(count) => {
+          let elements = self.stack.split_off(self.stack.len() - count);
+          self.push(Value::List(Rc::new(LoxList::new(elements))));
+        }
+        OpCode::Map(count) => {
+          let map = LoxMap::default();
+          let entries = self.stack.split_off(self.stack.len() - 2 * count);
+          for pair in entries.chunks_exact(2) {
+            match pair[0].as_key() {
+              Ok(key) => map.insert(key, pair[1].clone()),
+              Err(kind) => return self.runtime_error(kind),
+            }
+          }
+          self.push(Value::Map(Rc::new(map)));
+        }
+
+        OpCode::Class(idx) => {
+          let name = self.read_name(idx);
+          let span = self.frame().closure.function.chunk.spans[self.frame().ip - 1];
+          let class = LoxClass::new((name, span).into(), None, HashMap::new());
+          self.push(Value::Class(Rc::new(class)));
+        }
+        OpCode::Inherit => {
+          let Value::Class(superclass) = self.peek(1).clone() else {
+            return self.runtime_error(ErrorKind::SuperclassNotClass);
+          };
+          let Value::Class(subclass) = self.pop() else {
+            unreachable!("expect a class");
+          };
+          subclass.inherit(&superclass);
+        }
+        OpCode::Method(idx) => {
+          let name = self.read_name(idx);
+          let method = self.pop();
+          let Value::Class(class) = self.peek(0) else {
+            unreachable!("expect a class");
+          };
+          class.add_method(name, method);
+        }
+      }
+    }
+  }
+
+  fn binary_op(
+    &mut self,
+    op: fn(&LiteralValue, &LiteralValue) -> Result<LiteralValue, OperandError>,
+  ) -> RunResult {
+    let result = match (self.peek(1), self.peek(0)) {
+      (Value::Literal(a), Value::Literal(b)) => op(a, b),
+      _ => Err(OperandError::ExpectNumbers),
+    };
+    match result {
+      Ok(value) => {
+        self.pop();
+        self.pop();
+        self.push(value.into());
+        Ok(())
+      }
+      Err(e) => self.runtime_error(ErrorKind::TypeMismatch(e)),
+    }
+  }
+
+  fn comparison_op(&mut self, op: fn(f64, f64) -> bool) -> RunResult {
+    let result = match (self.peek(1), self.peek(0)) {
+      (Value::Literal(a), Value::Literal(b)) => a.check_both_numeric(b),
+      _ => Err(OperandError::ExpectNumbers),
+    };
+    match result {
+      Ok((a, b)) => {
+        self.pop();
+        self.pop();
+        self.push(op(a, b).into());
+        Ok(())
+      }
+      Err(e) => self.runtime_error(ErrorKind::TypeMismatch(e)),
+    }
+  }
+
+  fn bind_method(&mut self, class: &LoxClass, name: &str, receiver: Value) -> RunResult<Value> {
+    match class.find_method(name) {
+      Some(Value::Closure(method)) => Ok(Value::BoundMethod(Rc::new(BoundMethod {
+        receiver,
+        method,
+      }))),
+      _ => self.runtime_error(ErrorKind::UndefinedProperty(name.to_string())),
+    }
+  }
+
+  /// Calls the callee below the arguments on the stack.
+  fn call_value(&mut self, arg_count: usize) -> RunResult {
+    let callee_slot = self.stack.len() - arg_count - 1;
+    match self.stack[callee_slot].clone() {
+      Value::Closure(closure) => self.call(closure, arg_count),
+      Value::BoundMethod(bound) => {
+        self.stack[callee_slot] = bound.receiver.clone();
+        self.call(bound.method.clone(), arg_count)
+      }
+      Value::Class(class) => {
+        self.stack[callee_slot] = Value::Instance(Rc::new(LoxInstance::new(class.clone())));
+        match class.find_method("init") {
+          Some(Value::Closure(init)) => self.call(init, arg_count),
+          _ if arg_count != 0 => self.runtime_error(ErrorKind::ArityMismatch {
+            expected: 0,
+            found: arg_count,
+          }),
+          _ => Ok(()),
+        }
+      }
+      Value::Native(native) => {
+        if arg_count != native.arity() {
+          return self.runtime_error(ErrorKind::ArityMismatch {
+            expected: native.arity(),
+            found: arg_count,
+          });
+        }
+        match native.call(&self.stack[callee_slot + 1..]) {
+          Ok(value) => {
+            self.stack.truncate(callee_slot);
+            self.push(value);
+            Ok(())
+          }
+          Err(message) => self.runtime_error(ErrorKind::Native(message)),
+        }
+      }
+      _ => self.runtime_error(ErrorKind::NotCallable),
+    }
+  }
+
+  fn call(&mut self, closure: Rc<Closure>, arg_count: usize) -> RunResult {
+    if arg_count != closure.function.arity {
+      return self.runtime_error(ErrorKind::ArityMismatch {
+        expected: closure.function.arity,
+        found: arg_count,
+      });
+    }
+    if self.frames.len() == FRAMES_MAX {
+      return self.runtime_error(ErrorKind::StackOverflow);
+    }
+    self.frames.push(CallFrame {
+      closure,
+      ip: 0,
+      slots: self.stack.len() - arg_count - 1,
+    });
+    Ok(())
+  }
+
+  fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+    let existing = self
+      .open_upvalues
+      .iter()
+      .find(|uv| matches!(&*uv.borrow(), Upvalue::Open(s) if *s == slot));
+    if let Some(upvalue) = existing {
+      return upvalue.clone();
+    }
+    let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+    self.open_upvalues.push(upvalue.clone());
+    upvalue
+  }
+
+  /// Moves captured variables at or above `from` off the stack.
+  fn close_upvalues(&mut self, from: usize) {
+    let stack = &self.stack;
+    self.open_upvalues.retain(|upvalue| {
+      let slot = match &*upvalue.borrow() {
+        Upvalue::Open(slot) if *slot >= from => *slot,
+        _ => return true,
+      };
+      *upvalue.borrow_mut() = Upvalue::Closed(stack[slot].clone());
+      false
+    });
+  }
+}
@@ -1,7 +1,126 @@
-//! Error reporters.
+//! Errors raised by the runtimes.
 
-pub fn report<T>(line: usize, msg: &str) -> Option<T> {
-  eprintln!("{msg}");
-  eprintln!("[line {line}]");
-  None
+use ember_lox_ast::ast::prelude::OperandError;
+use ember_lox_parse::error::Diagnostic;
+use ember_lox_tokenizer::Span;
+use std::fmt;
+
+/// A runtime error, with where it's raised and the calls leading to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+  pub kind: ErrorKind,
+  /// The expression (or token) which fails.
+  pub span: Span,
+  /// Active calls when the error is raised, innermost first.
+  pub trace: Vec<TraceFrame>,
+}
+
+/// One active call in [RuntimeError::trace].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+  /// Name of the called function, `None` for the top-level script.
+  pub function: Option<String>,
+  /// Where the frame is executing, i.e. the call site of the inner frame.
+  pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+  /// Operands of an operator are of the wrong types.
+  TypeMismatch(OperandError),
+  UndefinedVariable(String),
+  UndefinedProperty(String),
+  /// Getting a property of a non-instance.
+  NoProperties,
+  /// Setting a field of a non-instance.
+  NoFields,
+  ArityMismatch {
+    expected: usize,
+    found: usize,
+  },
+  NotCallable,
+  /// Indexing a value other than lists and maps.
+  NotIndexable,
+  /// An index which isn't an integer, with the type (or value) of it.
+  InvalidIndex(String),
+  IndexOutOfBounds {
+    index: f64,
+    len: usize,
+  },
+  /// A map key which isn't a literal, with the type of it.
+  InvalidKey(String),
+  /// Getting a missing key of a map, which is shown as it's written.
+  UndefinedKey(String),
+  SuperclassNotClass,
+  /// Using `this` outside of a class, which is usually caught by the resolver.
+  InvalidThis,
+  /// Using `super` outside of a subclass, which is usually caught by the resolver.
+  InvalidSuper,
+  /// Too many nested calls.
+  StackOverflow,
+  /// Raised by a [NativeFunction](crate::native::NativeFunction), with its message.
+  Native(String),
+}
+
+impl RuntimeError {
+  pub fn new(kind: ErrorKind, span: Span, trace: Vec<TraceFrame>) -> Self {
+    Self { kind, span, trace }
+  }
+}
+
+impl fmt::Display for ErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ErrorKind::TypeMismatch(e) => write!(f, "{}", e),
+      ErrorKind::UndefinedVariable(name) => write!(f, "Undefined variable: '{}'.", name),
+      ErrorKind::UndefinedProperty(name) => write!(f, "Undefined property '{}'.", name),
+      ErrorKind::NoProperties => write!(f, "Only instances have properties."),
+      ErrorKind::NoFields => write!(f, "Only instances have fields."),
+      ErrorKind::ArityMismatch { expected, found } => {
+        write!(f, "Expected {} arguments but got {}.", expected, found)
+      }
+      ErrorKind::NotCallable => write!(f, "Can only call functions and classes."),
+      ErrorKind::NotIndexable => write!(f, "Only lists and maps can be indexed."),
+      ErrorKind::InvalidIndex(found) => write!(f, "Index must be an integer but got {}.", found),
+      ErrorKind::IndexOutOfBounds { index, len } => {
+        write!(f, "Index {} is out of bounds for length {}.", index, len)
+      }
+      ErrorKind::InvalidKey(found) => write!(
+        f,
+        "Map keys must be strings, numbers, booleans or nil but got {}.",
+        found
+      ),
+      ErrorKind::UndefinedKey(key) => write!(f, "Undefined key {}.", key),
+      ErrorKind::SuperclassNotClass => write!(f, "Superclass must be a class."),
+      ErrorKind::InvalidThis => write!(f, "Can't use 'this' outside of a class."),
+      ErrorKind::InvalidSuper => write!(f, "Can't use 'super' outside of a subclass."),
+      ErrorKind::StackOverflow => write!(f, "Stack overflow."),
+      ErrorKind::Native(message) => write!(f, "{}", message),
+    }
+  }
+}
+
+/// `[line 3] in foo()`, or `[line 7] in script`.
+impl fmt::Display for TraceFrame {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.function {
+      Some(name) => write!(f, "[line {}] in {}()", self.span.line, name),
+      None => write!(f, "[line {}] in script", self.span.line),
+    }
+  }
+}
+
+impl fmt::Display for RuntimeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.kind)
+  }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl From<&RuntimeError> for Diagnostic {
+  fn from(error: &RuntimeError) -> Self {
+    let trace = error.trace.iter().map(ToString::to_string).collect();
+    Diagnostic::runtime(error.to_string(), error.span).with_trace(trace)
+  }
 }
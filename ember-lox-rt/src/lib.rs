@@ -4,10 +4,19 @@ use std::sync::{Arc, LazyLock, Weak};
 pub mod ast_interpreter;
 pub mod bytecode_vm;
 pub mod error;
+pub mod native;
+pub mod object;
+pub mod resolver;
+pub mod runtime;
+pub mod stdlib;
+pub mod value;
 
 /// The maximum length of a string to be interned.
 pub const MAX_INTERN_STR_LEN: usize = 128;
 
+/// Name of every lambda, e.g. in `<fn lambda>` and stack traces.
+pub const LAMBDA_NAME: &str = "lambda";
+
 pub mod prelude {
   pub use super::{intern_string, MAX_INTERN_STR_LEN};
 }
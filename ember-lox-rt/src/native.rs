@@ -0,0 +1,64 @@
+//! Functions implemented in Rust, which are shared by the execution backends.
+
+use crate::value::Value;
+use std::{fmt::Debug, sync::Arc, time::SystemTime};
+
+/// The body of a [NativeFunction], which gets exactly `arity` arguments.
+///
+/// `Err` carries the message of a runtime error.
+pub type NativeFn = dyn Fn(&[Value]) -> Result<Value, String>;
+
+/// A function provided by the host.
+pub struct NativeFunction {
+  name: Arc<str>,
+  arity: usize,
+  function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+  pub fn new(
+    name: &str,
+    arity: usize,
+    function: impl Fn(&[Value]) -> Result<Value, String> + 'static,
+  ) -> Self {
+    Self {
+      name: name.into(),
+      arity,
+      function: Box::new(function),
+    }
+  }
+
+  pub fn name(&self) -> &Arc<str> {
+    &self.name
+  }
+
+  pub fn arity(&self) -> usize {
+    self.arity
+  }
+
+  /// Calls with `args`, whose length should equal to [NativeFunction::arity].
+  pub fn call(&self, args: &[Value]) -> Result<Value, String> {
+    (self.function)(args)
+  }
+}
+
+impl Debug for NativeFunction {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "<native fn {}>", self.name)
+  }
+}
+
+/// Natives defined in every runtime.
+pub fn defaults() -> Vec<NativeFunction> {
+  vec![clock()]
+}
+
+/// `clock()`, seconds since the Unix epoch.
+pub fn clock() -> NativeFunction {
+  NativeFunction::new("clock", 0, |_| {
+    let elapsed = SystemTime::now()
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .map_err(|e| e.to_string())?;
+    Ok(elapsed.as_secs_f64().into())
+  })
+}
@@ -0,0 +1,206 @@
+//! Heap objects shared by the execution backends.
+
+use crate::{error::ErrorKind, value::Value};
+use ember_lox_ast::{ast::prelude::LiteralValue, STR};
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc};
+
+#[derive(Debug)]
+pub struct LoxClass {
+  name: STR,
+  superclass: Option<Rc<LoxClass>>,
+  /// Methods are backend-specific callables,
+  /// i.e. [Value::Function] (`tree-walk`) or [Value::Closure] (`bytecode`).
+  methods: RefCell<HashMap<Arc<str>, Value>>,
+}
+
+impl LoxClass {
+  pub fn new(
+    name: STR,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<Arc<str>, Value>,
+  ) -> Self {
+    Self {
+      name,
+      superclass,
+      methods: RefCell::new(methods),
+    }
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name.0
+  }
+
+  /// Looks up the method through the inheritance chain.
+  pub fn find_method(&self, name: &str) -> Option<Value> {
+    match self.methods.borrow().get(name) {
+      Some(method) => Some(method.clone()),
+      None => self.superclass.as_ref()?.find_method(name),
+    }
+  }
+
+  pub fn add_method(&self, name: Arc<str>, method: Value) {
+    self.methods.borrow_mut().insert(name, method);
+  }
+
+  /// Copies down all methods of `superclass`, which could be overridden afterwards.
+  pub fn inherit(&self, superclass: &LoxClass) {
+    let inherited = superclass.methods.borrow().clone();
+    self.methods.borrow_mut().extend(inherited);
+  }
+}
+
+#[derive(Debug)]
+pub struct LoxInstance {
+  class: Rc<LoxClass>,
+  fields: RefCell<HashMap<Arc<str>, Value>>,
+}
+
+impl LoxInstance {
+  pub fn new(class: Rc<LoxClass>) -> Self {
+    Self {
+      class,
+      fields: RefCell::new(HashMap::new()),
+    }
+  }
+
+  pub fn class(&self) -> &Rc<LoxClass> {
+    &self.class
+  }
+
+  pub fn field(&self, name: &str) -> Option<Value> {
+    self.fields.borrow().get(name).cloned()
+  }
+
+  pub fn set(&self, name: Arc<str>, value: Value) {
+    self.fields.borrow_mut().insert(name, value);
+  }
+}
+
+/// A growable list, shared by all references to it.
+#[derive(Debug, Default)]
+pub struct LoxList {
+  elements: RefCell<Vec<Value>>,
+}
+
+impl LoxList {
+  pub fn new(elements: Vec<Value>) -> Self {
+    Self {
+      elements: RefCell::new(elements),
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.elements.borrow().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.elements.borrow().is_empty()
+  }
+
+  /// Checks that `index` is an integer within the bounds.
+  fn position(&self, index: &Value) -> Result<usize, ErrorKind> {
+    let Value::Literal(LiteralValue::Number(n)) = index else {
+      return Err(ErrorKind::InvalidIndex(index.get_type().to_string()));
+    };
+    if n.fract() != 0.0 {
+      return Err(ErrorKind::InvalidIndex(n.to_string()));
+    }
+    if *n < 0.0 || *n >= self.len() as f64 {
+      return Err(ErrorKind::IndexOutOfBounds {
+        index: *n,
+        len: self.len(),
+      });
+    }
+    Ok(*n as usize)
+  }
+
+  pub fn get(&self, index: &Value) -> Result<Value, ErrorKind> {
+    let i = self.position(index)?;
+    Ok(self.elements.borrow()[i].clone())
+  }
+
+  pub fn set(&self, index: &Value, value: Value) -> Result<(), ErrorKind> {
+    let i = self.position(index)?;
+    self.elements.borrow_mut()[i] = value;
+    Ok(())
+  }
+
+  pub fn push(&self, value: Value) {
+    self.elements.borrow_mut().push(value);
+  }
+
+  pub fn pop(&self) -> Option<Value> {
+    self.elements.borrow_mut().pop()
+  }
+
+  /// A copy of the elements, which won't be affected by later changes.
+  pub fn elements(&self) -> Vec<Value> {
+    self.elements.borrow().clone()
+  }
+}
+
+/// A map from literals to values, shared by all references to it.
+///
+/// Keys are compared with `PartialEq` for [LiteralValue] (numbers aren't hashable),
+/// and entries are kept in the order of insertion.
+#[derive(Debug, Default)]
+pub struct LoxMap {
+  entries: RefCell<Vec<(LiteralValue, Value)>>,
+}
+
+impl LoxMap {
+  pub fn len(&self) -> usize {
+    self.entries.borrow().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.borrow().is_empty()
+  }
+
+  fn position(&self, key: &LiteralValue) -> Option<usize> {
+    self.entries.borrow().iter().position(|(k, _)| k == key)
+  }
+
+  pub fn contains(&self, key: &LiteralValue) -> bool {
+    self.position(key).is_some()
+  }
+
+  pub fn get(&self, key: &Value) -> Result<Value, ErrorKind> {
+    let key = key.as_key()?;
+    match self.position(&key) {
+      Some(i) => Ok(self.entries.borrow()[i].1.clone()),
+      None => Err(ErrorKind::UndefinedKey(key_repr(&key))),
+    }
+  }
+
+  /// Inserts, or updates the entry in place.
+  pub fn set(&self, key: &Value, value: Value) -> Result<(), ErrorKind> {
+    self.insert(key.as_key()?, value);
+    Ok(())
+  }
+
+  pub fn insert(&self, key: LiteralValue, value: Value) {
+    match self.position(&key) {
+      Some(i) => self.entries.borrow_mut()[i].1 = value,
+      None => self.entries.borrow_mut().push((key, value)),
+    }
+  }
+
+  pub fn remove(&self, key: &LiteralValue) -> Option<Value> {
+    let i = self.position(key)?;
+    Some(self.entries.borrow_mut().remove(i).1)
+  }
+
+  /// A copy of the entries, which won't be affected by later changes.
+  pub fn entries(&self) -> Vec<(LiteralValue, Value)> {
+    self.entries.borrow().clone()
+  }
+}
+
+/// How a key is shown, e.g. `"a"` or `1`.
+pub fn key_repr(key: &LiteralValue) -> String {
+  match key {
+    LiteralValue::String(s) => format!("\"{}\"", s),
+    _ => key.to_string(),
+  }
+}
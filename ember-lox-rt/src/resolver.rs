@@ -0,0 +1,375 @@
+//! A static pass over the `AST`, which runs before interpretation.
+//!
+//! It resolves every variable use to the scope declaring it (see [ScopeDepth]),
+//! and reports errors that could be detected without running the program.
+
+use ember_lox_ast::{
+  ast::prelude::*,
+  visit::{Visitor, VisitorAcceptor},
+  STR,
+};
+use ember_lox_parse::error::Diagnostic;
+use ember_lox_tokenizer::Span;
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionKind {
+  None,
+  Function,
+  Initializer,
+  Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassKind {
+  None,
+  Class,
+  Subclass,
+}
+
+/// A local variable in a scope of [Resolver].
+#[derive(Debug, Clone, Copy)]
+struct Local {
+  /// Where it's declared.
+  span: Span,
+  /// Whether its initializer has been resolved.
+  is_defined: bool,
+}
+
+pub struct Resolver {
+  /// Local scopes, the innermost one is at the back (globals are not tracked).
+  scopes: Vec<HashMap<Arc<str>, Local>>,
+  current_function: FunctionKind,
+  current_class: ClassKind,
+  diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Resolver {
+  fn default() -> Self {
+    Self {
+      scopes: vec![],
+      current_function: FunctionKind::None,
+      current_class: ClassKind::None,
+      diagnostics: vec![],
+    }
+  }
+}
+
+impl Resolver {
+  /// Resolves all `roots`, returns all errors found (if any).
+  pub fn resolve(&mut self, roots: &[Stmt]) -> Result<(), Vec<Diagnostic>> {
+    self.resolve_stmts(roots);
+    if self.diagnostics.is_empty() {
+      Ok(())
+    } else {
+      Err(std::mem::take(&mut self.diagnostics))
+    }
+  }
+
+  fn resolve_stmts(&mut self, stmts: &[Stmt]) {
+    for stmt in stmts {
+      stmt.accept(self);
+    }
+  }
+
+  fn error(&mut self, span: Span, lexeme: &str, msg: &str) {
+    self
+      .diagnostics
+      .push(Diagnostic::at(Some(lexeme), msg, span));
+  }
+
+  fn begin_scope(&mut self) {
+    self.scopes.push(HashMap::new());
+  }
+
+  fn end_scope(&mut self) {
+    self.scopes.pop();
+  }
+
+  fn declare(&mut self, name: &STR) {
+    let Some(scope) = self.scopes.last_mut() else {
+      return;
+    };
+    let local = Local {
+      span: name.1,
+      is_defined: false,
+    };
+    if let Some(previous) = scope.insert(name.0.to_owned(), local) {
+      let message = "Already a variable with this name in this scope.";
+      self.diagnostics.push(
+        Diagnostic::at(Some(&name.0), message, name.1)
+          .with_label(previous.span, "previously declared here"),
+      );
+    }
+  }
+
+  fn define(&mut self, name: &STR) {
+    if let Some(scope) = self.scopes.last_mut() {
+      let local = Local {
+        span: name.1,
+        is_defined: true,
+      };
+      scope.insert(name.0.to_owned(), local);
+    }
+  }
+
+  /// Records how far the declaring scope is, leaves `depth` untouched for globals.
+  fn resolve_local(&mut self, name: &str, depth: &ScopeDepth) {
+    for (distance, scope) in self.scopes.iter().rev().enumerate() {
+      if scope.contains_key(name) {
+        depth.set(distance);
+        return;
+      }
+    }
+  }
+
+  fn resolve_function(&mut self, params: &[STR], body: &[Stmt], kind: FunctionKind) {
+    let enclosing_function = self.current_function;
+    self.current_function = kind;
+
+    // Parameters and the body share the same scope.
+    self.begin_scope();
+    for param in params {
+      self.declare(param);
+      self.define(param);
+    }
+    self.resolve_stmts(body);
+    self.end_scope();
+
+    self.current_function = enclosing_function;
+  }
+}
+
+impl Visitor for Resolver {
+  type Output = ();
+
+  fn visit_stmt(&mut self, stmt: &Stmt) -> Self::Output {
+    use Stmt::*;
+
+    match stmt {
+      Block { stmts, .. } => {
+        self.begin_scope();
+        self.resolve_stmts(stmts);
+        self.end_scope();
+      }
+      Class {
+        name,
+        superclass,
+        methods,
+        ..
+      } => {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassKind::Class;
+        self.declare(name);
+        self.define(name);
+
+        if let Some((super_name, lookup)) = superclass {
+          if super_name.0 == name.0 {
+            self.error(
+              super_name.1,
+              &super_name.0,
+              "A class can't inherit from itself.",
+            );
+          }
+          self.current_class = ClassKind::Subclass;
+          if let Some(lookup) = lookup {
+            lookup.accept(self);
+          }
+          self.begin_scope();
+          self.define(&("super", super_name.1).into());
+        }
+
+        self.begin_scope();
+        self.define(&("this", name.1).into());
+        for (method_name, params, body) in methods {
+          let kind = if method_name.0.as_ref() == "init" {
+            FunctionKind::Initializer
+          } else {
+            FunctionKind::Method
+          };
+          self.resolve_function(params, body, kind);
+        }
+        self.end_scope();
+
+        if superclass.is_some() {
+          self.end_scope();
+        }
+        self.current_class = enclosing_class;
+      }
+      Expression { expr, .. } => expr.accept(self),
+      Function {
+        name, params, body, ..
+      } => {
+        // Define eagerly, so that the function could refer to itself.
+        self.declare(name);
+        self.define(name);
+        self.resolve_function(params, body, FunctionKind::Function);
+      }
+      If {
+        cond,
+        then_branch,
+        else_branch,
+        ..
+      } => {
+        cond.accept(self);
+        then_branch.accept(self);
+        if let Some(else_branch) = else_branch {
+          else_branch.accept(self);
+        }
+      }
+      Print { expr, .. } => expr.accept(self),
+      Return { keyword, value, .. } => {
+        let lexeme = keyword.0.to_string();
+        if self.current_function == FunctionKind::None {
+          self.error(keyword.1, &lexeme, "Can't return from top-level code.");
+        }
+        if let Some(value) = value {
+          if self.current_function == FunctionKind::Initializer {
+            let message = "Can't return a value from an initializer.";
+            self.diagnostics.push(
+              Diagnostic::at(Some(&lexeme), message, keyword.1)
+                .with_note("an initializer always returns 'this'"),
+            );
+          }
+          value.accept(self);
+        }
+      }
+      Variable {
+        name, initializer, ..
+      } => {
+        // Split the binding into two steps, to detect self-reference in initializer.
+        self.declare(name);
+        if let Some(initializer) = initializer {
+          initializer.accept(self);
+        }
+        self.define(name);
+      }
+      While { cond, body, .. } => {
+        cond.accept(self);
+        body.accept(self);
+      }
+      // The initializer lives in a scope of the loop.
+      For {
+        initializer,
+        cond,
+        increment,
+        body,
+        ..
+      } => {
+        self.begin_scope();
+        if let Some(initializer) = initializer {
+          initializer.accept(self);
+        }
+        cond.accept(self);
+        if let Some(increment) = increment {
+          increment.accept(self);
+        }
+        body.accept(self);
+        self.end_scope();
+      }
+      // Misplaced ones are reported by the parser.
+      Break { .. } | Continue { .. } => {}
+    }
+  }
+
+  fn visit_expr(&mut self, expr: &Expr) -> Self::Output {
+    use Expr::*;
+
+    match expr {
+      Assign {
+        name, val, depth, ..
+      } => {
+        val.accept(self);
+        self.resolve_local(&name.0, depth);
+      }
+      Binary { left, right, .. } | Logical { left, right, .. } => {
+        left.accept(self);
+        right.accept(self);
+      }
+      Call { callee, args, .. } => {
+        callee.accept(self);
+        args.iter().for_each(|arg| arg.accept(self));
+      }
+      Get { obj, .. } => obj.accept(self),
+      Grouping { expr, .. } => expr.accept(self),
+      Interpolation { parts, .. } => parts.iter().for_each(|p| p.accept(self)),
+      Lambda { params, body, .. } => self.resolve_function(params, body, FunctionKind::Function),
+      Index { obj, index, .. } => {
+        obj.accept(self);
+        index.accept(self);
+      }
+      List { elements, .. } => elements.iter().for_each(|e| e.accept(self)),
+      Literal { .. } => {}
+      Map { entries, .. } => entries.iter().for_each(|(key, val)| {
+        key.accept(self);
+        val.accept(self);
+      }),
+      Set { obj, val, .. } => {
+        val.accept(self);
+        obj.accept(self);
+      }
+      SetIndex {
+        obj, index, val, ..
+      } => {
+        obj.accept(self);
+        index.accept(self);
+        val.accept(self);
+      }
+      Super { keyword, depth, .. } => {
+        match self.current_class {
+          ClassKind::None => self.error(
+            keyword.1,
+            &keyword.0,
+            "Can't use 'super' outside of a class.",
+          ),
+          ClassKind::Class => self.error(
+            keyword.1,
+            &keyword.0,
+            "Can't use 'super' in a class with no superclass.",
+          ),
+          ClassKind::Subclass => {}
+        }
+        self.resolve_local(&keyword.0, depth);
+      }
+      This { keyword, depth, .. } => {
+        if self.current_class == ClassKind::None {
+          self.error(
+            keyword.1,
+            &keyword.0,
+            "Can't use 'this' outside of a class.",
+          );
+          return;
+        }
+        self.resolve_local(&keyword.0, depth);
+      }
+      Ternary {
+        cond,
+        then_branch,
+        else_branch,
+        ..
+      } => {
+        cond.accept(self);
+        then_branch.accept(self);
+        else_branch.accept(self);
+      }
+      Unary { right, .. } => right.accept(self),
+      Update { target, val, .. } => {
+        target.accept(self);
+        if let Some(val) = val {
+          val.accept(self);
+        }
+      }
+      Var { name, depth, .. } => {
+        let local = self.scopes.last().and_then(|scope| scope.get(&name.0));
+        if local.is_some_and(|local| !local.is_defined) {
+          self.error(
+            name.1,
+            &name.0,
+            "Can't read local variable in its own initializer.",
+          );
+        }
+        self.resolve_local(&name.0, depth);
+      }
+    }
+  }
+}
@@ -0,0 +1,55 @@
+//! The common interface of all execution backends.
+
+use crate::{
+  ast_interpreter::Interpreter, bytecode_vm::Vm, error::RuntimeError, native::NativeFunction,
+};
+use ember_lox_ast::ast::prelude::*;
+use std::str::FromStr;
+
+/// Maximum depth of nested calls, including the top-level script.
+pub const FRAMES_MAX: usize = 1024;
+
+/// An execution backend, which keeps its state (e.g. globals) between runs.
+pub trait Runtime {
+  /// Runs `roots`, which should have been resolved by [Resolver](crate::resolver::Resolver).
+  ///
+  /// In `REPL` mode, the value of each expression statement is printed.
+  ///
+  /// Stops at the first runtime error, which is returned.
+  fn interpret(&mut self, roots: &[Stmt], is_in_repl: bool) -> Result<(), RuntimeError>;
+
+  /// Defines `native` as a global, which replaces the existing one with the same name.
+  fn define_native(&mut self, native: NativeFunction);
+}
+
+/// Selects which [Runtime] to use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+  /// The tree-walk [Interpreter].
+  #[default]
+  Tree,
+  /// The bytecode [Vm].
+  Vm,
+}
+
+impl Backend {
+  /// Creates a fresh runtime of this backend.
+  pub fn runtime(self) -> Box<dyn Runtime> {
+    match self {
+      Backend::Tree => Box::new(Interpreter::default()),
+      Backend::Vm => Box::new(Vm::default()),
+    }
+  }
+}
+
+impl FromStr for Backend {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "tree" => Ok(Backend::Tree),
+      "vm" => Ok(Backend::Vm),
+      _ => Err(format!("Unknown backend: {}", s)),
+    }
+  }
+}
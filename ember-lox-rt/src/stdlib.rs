@@ -0,0 +1,219 @@
+//! The standard library, which is optional (e.g. the `codecrafters` tests expect none of it).
+//!
+//! Natives here are built on [LiteralValue]s (i.e. strings, numbers and so on), lists and maps.
+
+use crate::{
+  native::NativeFunction,
+  object::{LoxList, LoxMap},
+  value::Value,
+};
+use ember_lox_ast::ast::prelude::*;
+use std::{cell::Cell, rc::Rc, time::SystemTime};
+
+/// All natives of the standard library.
+pub fn natives() -> Vec<NativeFunction> {
+  let mut natives = vec![
+    // Strings
+    NativeFunction::new("len", 1, |args| match &args[0] {
+      Value::Literal(LiteralValue::String(s)) => Ok((s.chars().count() as f64).into()),
+      Value::List(list) => Ok((list.len() as f64).into()),
+      Value::Map(map) => Ok((map.len() as f64).into()),
+      v => Err(expected("len", "string, list or map", v)),
+    }),
+    NativeFunction::new("substr", 3, |args| {
+      let s = string("substr", &args[0])?;
+      let start = index("substr", &args[1])?;
+      let end = index("substr", &args[2])?;
+      let len = s.chars().count();
+      if start > end || end > len {
+        return Err(format!(
+          "substr() expects 0 <= start <= end <= {} but got {} and {}.",
+          len, start, end
+        ));
+      }
+      let sub = s.chars().skip(start).take(end - start).collect::<String>();
+      Ok(sub.as_str().into())
+    }),
+    NativeFunction::new("split", 2, |args| {
+      let s = string("split", &args[0])?;
+      let separator = string("split", &args[1])?;
+      let parts: Vec<Value> = if separator.is_empty() {
+        s.chars().map(|c| c.to_string().as_str().into()).collect()
+      } else {
+        s.split(separator).map(Value::from).collect()
+      };
+      Ok(new_list(parts))
+    }),
+    NativeFunction::new("upper", 1, |args| {
+      Ok(string("upper", &args[0])?.to_uppercase().as_str().into())
+    }),
+    NativeFunction::new("lower", 1, |args| {
+      Ok(string("lower", &args[0])?.to_lowercase().as_str().into())
+    }),
+    // Lists
+    NativeFunction::new("push", 2, |args| {
+      list("push", &args[0])?.push(args[1].clone());
+      Ok(Value::nil())
+    }),
+    NativeFunction::new("pop", 1, |args| {
+      list("pop", &args[0])?
+        .pop()
+        .ok_or_else(|| "pop() expects a non-empty list.".to_string())
+    }),
+    // Maps
+    NativeFunction::new("keys", 1, |args| {
+      let entries = map("keys", &args[0])?.entries();
+      Ok(new_list(entries.into_iter().map(|(k, _)| k.into())))
+    }),
+    NativeFunction::new("values", 1, |args| {
+      let entries = map("values", &args[0])?.entries();
+      Ok(new_list(entries.into_iter().map(|(_, v)| v)))
+    }),
+    // `[[key, value], ...]`
+    NativeFunction::new("entries", 1, |args| {
+      let entries = map("entries", &args[0])?.entries();
+      Ok(new_list(
+        entries.into_iter().map(|(k, v)| new_list([k.into(), v])),
+      ))
+    }),
+    NativeFunction::new("has", 2, |args| {
+      let key = key(&args[1])?;
+      Ok(map("has", &args[0])?.contains(&key).into())
+    }),
+    // The removed value, or `nil` if it's missing.
+    NativeFunction::new("remove", 2, |args| {
+      let key = key(&args[1])?;
+      Ok(
+        map("remove", &args[0])?
+          .remove(&key)
+          .unwrap_or_else(Value::nil),
+      )
+    }),
+    // Conversions
+    NativeFunction::new("str", 1, |args| Ok(args[0].to_string().as_str().into())),
+    NativeFunction::new("num", 1, |args| match &args[0] {
+      Value::Literal(LiteralValue::Number(n)) => Ok((*n).into()),
+      // Rust also accepts `inf` and `NaN`, which can't be written in Lox.
+      Value::Literal(LiteralValue::String(s)) => match s.trim().parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(n.into()),
+        _ => Err(format!("num() can't convert \"{}\" to a number.", s)),
+      },
+      v => Err(expected("num", "number or string", v)),
+    }),
+    NativeFunction::new("type_of", 1, |args| Ok(args[0].get_type().into())),
+    // Math
+    math("floor", f64::floor),
+    math("sqrt", f64::sqrt),
+    math("abs", f64::abs),
+    NativeFunction::new("pow", 2, |args| {
+      Ok(
+        number("pow", &args[0])?
+          .powf(number("pow", &args[1])?)
+          .into(),
+      )
+    }),
+    NativeFunction::new("min", 2, |args| {
+      Ok(
+        number("min", &args[0])?
+          .min(number("min", &args[1])?)
+          .into(),
+      )
+    }),
+    NativeFunction::new("max", 2, |args| {
+      Ok(
+        number("max", &args[0])?
+          .max(number("max", &args[1])?)
+          .into(),
+      )
+    }),
+  ];
+  natives.extend(random());
+  natives
+}
+
+/// `random()` and `seed(n)`, which share one generator.
+fn random() -> [NativeFunction; 2] {
+  let now = SystemTime::now()
+    .duration_since(SystemTime::UNIX_EPOCH)
+    .map_or(0, |d| d.as_nanos() as u64);
+  let state = Rc::new(Cell::new(now));
+
+  let seeded = state.clone();
+  [
+    // A number in `[0, 1)`.
+    NativeFunction::new("random", 0, move |_| {
+      let next = split_mix64(&state);
+      Ok(((next >> 11) as f64 / (1u64 << 53) as f64).into())
+    }),
+    NativeFunction::new("seed", 1, move |args| {
+      seeded.set(number("seed", &args[0])?.to_bits());
+      Ok(Value::nil())
+    }),
+  ]
+}
+
+/// See <https://prng.di.unimi.it/splitmix64.c>.
+fn split_mix64(state: &Cell<u64>) -> u64 {
+  state.set(state.get().wrapping_add(0x9e3779b97f4a7c15));
+  let mut z = state.get();
+  z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+  z ^ (z >> 31)
+}
+
+fn math(name: &'static str, f: fn(f64) -> f64) -> NativeFunction {
+  NativeFunction::new(name, 1, move |args| Ok(f(number(name, &args[0])?).into()))
+}
+
+fn expected(name: &str, expected: &str, got: &Value) -> String {
+  format!(
+    "{}() expects a {} but got {}.",
+    name,
+    expected,
+    got.get_type()
+  )
+}
+
+fn number(name: &str, value: &Value) -> Result<f64, String> {
+  match value {
+    Value::Literal(LiteralValue::Number(n)) => Ok(*n),
+    v => Err(expected(name, "number", v)),
+  }
+}
+
+fn string<'v>(name: &str, value: &'v Value) -> Result<&'v str, String> {
+  match value {
+    Value::Literal(LiteralValue::String(s)) => Ok(s),
+    v => Err(expected(name, "string", v)),
+  }
+}
+
+fn list<'v>(name: &str, value: &'v Value) -> Result<&'v LoxList, String> {
+  match value {
+    Value::List(list) => Ok(list),
+    v => Err(expected(name, "list", v)),
+  }
+}
+
+fn map<'v>(name: &str, value: &'v Value) -> Result<&'v LoxMap, String> {
+  match value {
+    Value::Map(map) => Ok(map),
+    v => Err(expected(name, "map", v)),
+  }
+}
+
+fn key(value: &Value) -> Result<LiteralValue, String> {
+  value.as_key().map_err(|kind| kind.to_string())
+}
+
+fn new_list(elements: impl IntoIterator<Item = Value>) -> Value {
+  Value::List(Rc::new(LoxList::new(elements.into_iter().collect())))
+}
+
+/// A non-negative integer.
+fn index(name: &str, value: &Value) -> Result<usize, String> {
+  match number(name, value)? {
+    n if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+    n => Err(format!("{}() expects an index but got {}.", name, n)),
+  }
+}
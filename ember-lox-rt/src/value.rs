@@ -0,0 +1,220 @@
+//! Runtime values.
+
+use crate::{
+  ast_interpreter::callable::LoxFunction,
+  bytecode_vm::object::{BoundMethod, Closure},
+  error::ErrorKind,
+  native::NativeFunction,
+  object::{key_repr, LoxClass, LoxInstance, LoxList, LoxMap},
+};
+use ember_lox_ast::ast::prelude::*;
+use std::{
+  fmt::{Debug, Display},
+  rc::Rc,
+};
+
+/// A value that could be produced while running a program.
+///
+/// Unlike [LiteralValue], which could be written down in the source code,
+/// [Value] also covers the objects that only exist at runtime (e.g. functions).
+#[derive(Clone)]
+pub enum Value {
+  /// `nil`, booleans, numbers and strings.
+  Literal(LiteralValue),
+  /// A user-defined function, together with its closure (`tree-walk` backend).
+  Function(Rc<LoxFunction>),
+  /// A compiled function, together with its upvalues (`bytecode` backend).
+  Closure(Rc<Closure>),
+  /// A method bound to its receiver (`bytecode` backend).
+  BoundMethod(Rc<BoundMethod>),
+  /// A function provided by the host.
+  Native(Rc<NativeFunction>),
+  /// A user-defined class.
+  Class(Rc<LoxClass>),
+  /// An instance of a user-defined class.
+  Instance(Rc<LoxInstance>),
+  /// A list, which is mutable and shared by reference.
+  List(Rc<LoxList>),
+  /// A map, which is mutable and shared by reference.
+  Map(Rc<LoxMap>),
+}
+
+impl Value {
+  pub fn nil() -> Self {
+    Value::Literal(LiteralValue::Nil)
+  }
+
+  pub fn is_true(&self) -> bool {
+    match self {
+      Value::Literal(l) => l.is_true(),
+      _ => true,
+    }
+  }
+
+  pub fn get_type(&self) -> &str {
+    match self {
+      Value::Literal(l) => l.get_type(),
+      Value::Function(_) | Value::Closure(_) | Value::BoundMethod(_) | Value::Native(_) => {
+        "function"
+      }
+      Value::Class(_) => "class",
+      Value::Instance(_) => "instance",
+      Value::List(_) => "list",
+      Value::Map(_) => "map",
+    }
+  }
+
+  /// Only literals could be the keys of a map.
+  pub fn as_key(&self) -> Result<LiteralValue, ErrorKind> {
+    match self {
+      Value::Literal(l) => Ok(l.clone()),
+      _ => Err(ErrorKind::InvalidKey(self.get_type().to_string())),
+    }
+  }
+
+  /// `self[index]`
+  pub fn get_index(&self, index: &Value) -> Result<Value, ErrorKind> {
+    match self {
+      Value::List(list) => list.get(index),
+      Value::Map(map) => map.get(index),
+      _ => Err(ErrorKind::NotIndexable),
+    }
+  }
+
+  /// `self[index] = value`
+  pub fn set_index(&self, index: &Value, value: Value) -> Result<(), ErrorKind> {
+    match self {
+      Value::List(list) => list.set(index, value),
+      Value::Map(map) => map.set(index, value),
+      _ => Err(ErrorKind::NotIndexable),
+    }
+  }
+}
+
+impl std::cmp::PartialEq for Value {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Value::Literal(a), Value::Literal(b)) => a == b,
+      // Objects are only equal to themselves,
+      // except for the same method bound to the same instance.
+      (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b) || a.is_same_bound_method(b),
+      (Value::Closure(a), Value::Closure(b)) => Rc::ptr_eq(a, b),
+      (Value::BoundMethod(a), Value::BoundMethod(b)) => {
+        Rc::ptr_eq(&a.method, &b.method) && a.receiver == b.receiver
+      }
+      (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+      (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+      (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+      (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+      (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+      _ => false,
+    }
+  }
+}
+
+impl Debug for Value {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Value::Literal(l) => write!(f, "{:?}", l),
+      _ => write!(f, "{}", self),
+    }
+  }
+}
+
+impl Display for Value {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Value::Literal(l) => write!(f, "{}", l),
+      Value::Function(func) => write!(f, "<fn {}>", func.name()),
+      Value::Closure(closure) if closure.function.name.is_empty() => write!(f, "<script>"),
+      Value::Closure(closure) => write!(f, "<fn {}>", closure.function.name),
+      Value::BoundMethod(bound) => write!(f, "<fn {}>", bound.method.function.name),
+      Value::Native(_) => write!(f, "<native fn>"),
+      Value::Class(class) => write!(f, "{}", class.name()),
+      Value::Instance(instance) => write!(f, "{} instance", instance.class().name()),
+      Value::List(list) => write_list(f, list, &mut vec![]),
+      Value::Map(map) => write_map(f, map, &mut vec![]),
+    }
+  }
+}
+
+/// Writes `value` inside a list or map, where strings are quoted.
+///
+/// `seen` are the enclosing containers, which are shown as `[...]` or `{...}`.
+fn write_nested(
+  f: &mut std::fmt::Formatter<'_>,
+  value: &Value,
+  seen: &mut Vec<*const ()>,
+) -> std::fmt::Result {
+  match value {
+    Value::Literal(l) => write!(f, "{}", key_repr(l)),
+    Value::List(list) => write_list(f, list, seen),
+    Value::Map(map) => write_map(f, map, seen),
+    _ => write!(f, "{}", value),
+  }
+}
+
+/// `[1, "a", [true]]`
+fn write_list(
+  f: &mut std::fmt::Formatter<'_>,
+  list: &Rc<LoxList>,
+  seen: &mut Vec<*const ()>,
+) -> std::fmt::Result {
+  let ptr = Rc::as_ptr(list).cast();
+  if seen.contains(&ptr) {
+    return write!(f, "[...]");
+  }
+  seen.push(ptr);
+  write!(f, "[")?;
+  for (i, element) in list.elements().iter().enumerate() {
+    if i > 0 {
+      write!(f, ", ")?;
+    }
+    write_nested(f, element, seen)?;
+  }
+  seen.pop();
+  write!(f, "]")
+}
+
+/// `{"a": 1, 2: [true]}`
+fn write_map(
+  f: &mut std::fmt::Formatter<'_>,
+  map: &Rc<LoxMap>,
+  seen: &mut Vec<*const ()>,
+) -> std::fmt::Result {
+  let ptr = Rc::as_ptr(map).cast();
+  if seen.contains(&ptr) {
+    return write!(f, "{{...}}");
+  }
+  seen.push(ptr);
+  write!(f, "{{")?;
+  for (i, (key, value)) in map.entries().iter().enumerate() {
+    if i > 0 {
+      write!(f, ", ")?;
+    }
+    write!(f, "{}: ", key_repr(key))?;
+    write_nested(f, value, seen)?;
+  }
+  seen.pop();
+  write!(f, "}}")
+}
+impl From<LiteralValue> for Value {
+  fn from(value: LiteralValue) -> Self {
+    Value::Literal(value)
+  }
+}
+impl From<bool> for Value {
+  fn from(value: bool) -> Self {
+    Value::Literal(value.into())
+  }
+}
+impl From<f64> for Value {
+  fn from(value: f64) -> Self {
+    Value::Literal(value.into())
+  }
+}
+impl From<&str> for Value {
+  fn from(value: &str) -> Self {
+    Value::Literal(value.into())
+  }
+}
@@ -2,6 +2,7 @@
 //!
 //! - [`rustc_lexer/src/cursor.rs`](https://github.com/rust-lang/rust/blob/master/compiler/rustc_lexer/src/cursor.rs)
 
+use crate::span::Span;
 use std::str::Chars;
 
 /// Peekable iterator over a char sequence.
@@ -14,6 +15,15 @@ pub struct Cursor<'src> {
   chars: Chars<'src>,
   prev_ch: char,
   line: usize,
+  /// Byte offset of the next character.
+  pos: usize,
+  /// Column of the next character.
+  col: usize,
+  /// Byte offset, line and column where the current token starts.
+  token_start: (usize, usize, usize),
+  /// Open `${` of interpolated strings, innermost last,
+  /// each with the number of unclosed `{` inside it.
+  pub(crate) interpolations: Vec<usize>,
 }
 
 pub(crate) const EOF_CHAR: char = '\0';
@@ -25,6 +35,20 @@ impl<'src> Cursor<'src> {
       chars: input.chars(),
       prev_ch: EOF_CHAR,
       line: 1,
+      pos: 0,
+      col: 1,
+      token_start: (0, 1, 1),
+      interpolations: vec![],
+    }
+  }
+
+  /// Like [Cursor::new], but `input` starts at byte offset `pos` and `line` of the whole source.
+  pub fn new_at(input: &'src str, pos: usize, line: usize) -> Cursor<'src> {
+    Cursor {
+      pos,
+      line,
+      token_start: (pos, line, 1),
+      ..Cursor::new(input)
     }
   }
 
@@ -81,11 +105,20 @@ impl<'src> Cursor<'src> {
   /// Resets the number of bytes consumed to `0`.
   pub fn reset_pos_within_token(&mut self) {
     self.len_remaining = self.chars.as_str().len();
+    self.token_start = (self.pos, self.line, self.col);
+  }
+
+  /// Returns the span of the current token (from its start to the next character).
+  pub fn token_span(&self) -> Span {
+    let (start, line, col) = self.token_start;
+    Span::new(start, self.pos, line, col)
   }
 
   /// Moves to the next character.
   pub fn bump(&mut self) -> Option<char> {
     let c = self.chars.next()?;
+    self.pos += c.len_utf8();
+    self.col = if c == '\n' { 1 } else { self.col + 1 };
 
     if cfg!(debug_assertions) {
       self.prev_ch = c;
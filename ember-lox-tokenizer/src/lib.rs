@@ -7,12 +7,17 @@
 //! - [`Build your own Interpreter`](https://app.codecrafters.io/courses/interpreter/overview)
 
 pub mod cursor;
+pub mod span;
 
 pub use cursor::Cursor;
+pub use span::Span;
 
 pub mod prelude {
   pub use super::cursor::Cursor;
-  pub use super::{tokenize, Base, LiteralKind, TagToken, TokenKind, TokenizationError};
+  pub use super::span::Span;
+  pub use super::{
+    tokenize, tokenize_at, Base, LiteralKind, TagToken, TokenKind, TokenizationError,
+  };
 }
 
 /// [`TagToken`] = Tag-only Token
@@ -26,20 +31,27 @@ pub struct TagToken {
   pub kind: TokenKind,
   pub len: usize,
   pub line: usize,
+  /// Where the token is located in the source text.
+  pub span: Span,
 }
 
 impl core::cmp::Eq for TagToken {}
 
 impl core::cmp::PartialEq for TagToken {
   fn eq(&self, other: &Self) -> bool {
-    // DON'T compare line
+    // DON'T compare line (or span)
     self.kind == other.kind && self.len == other.len
   }
 }
 
 impl TagToken {
-  pub fn new(kind: TokenKind, len: usize, line: usize) -> Self {
-    Self { kind, len, line }
+  pub fn new(kind: TokenKind, len: usize, line: usize, span: Span) -> Self {
+    Self {
+      kind,
+      len,
+      line,
+      span,
+    }
   }
 }
 
@@ -48,6 +60,9 @@ pub enum TokenKind {
   /// A line comment, e.g. `// comment`.
   LineComment,
 
+  /// A block comment, e.g. `/* comment */`, which could be nested and span multiple lines.
+  BlockComment,
+
   /// Any whitespace character sequence
   Whitespace,
 
@@ -68,6 +83,10 @@ pub enum TokenKind {
   Comma,
   /// `.`
   Dot,
+  /// `:`
+  Colon,
+  /// `?`
+  Question,
   /// `(`
   OpenParen,
   /// `)`
@@ -106,6 +125,23 @@ pub enum TokenKind {
   Star,
   /// `/`
   Slash,
+  /// `%`
+  Percent,
+  /// `**`
+  StarStar,
+
+  /// `-=`
+  MinusEq,
+  /// `+=`
+  PlusEq,
+  /// `*=`
+  StarEq,
+  /// `/=`
+  SlashEq,
+  /// `--`
+  MinusMinus,
+  /// `++`
+  PlusPlus,
 
   /// Tokenization Error
   TokErr(TokenizationError),
@@ -130,6 +166,11 @@ pub enum TokenizationError {
   /// e.g. `"Hello, World!`
   UnterminatedString { line: usize },
 
+  /// Unterminated block comment (loss the closing `*/` of it or a nested one)
+  ///
+  /// e.g. `/* outer /* inner */`
+  UnterminatedBlockComment { line: usize },
+
   /// An unknown literal prefix, like `foo#`, `foo'`, `foo"`. Excludes
   /// literal prefixes that contain emoji, which are considered "invalid".
   ///
@@ -140,6 +181,21 @@ pub enum TokenizationError {
   /// (allowed by default) lint, and are treated as regular identifier
   /// tokens.
   UnknownPrefix { line: usize },
+
+  /// A prefixed numeric literal without digits, e.g. `0x`.
+  EmptyDigits { base: Base, line: usize },
+
+  /// A digit not allowed by the base of the numeric literal, e.g. `0b102`.
+  InvalidDigit { ch: char, base: Base, line: usize },
+
+  /// An exponent without digits, e.g. `1e`, `2.5E+`.
+  EmptyExponent { line: usize },
+
+  /// A group of digits ending with the `_` separator, e.g. `1_`, `0xff_`, `1_.5`.
+  TrailingSeparator { base: Base, line: usize },
+
+  /// An unknown escape in a string, with the character after `\`, e.g. `q` of `"\q"`.
+  InvalidEscape { ch: char, line: usize },
 }
 
 impl TokenizationError {
@@ -148,7 +204,13 @@ impl TokenizationError {
       InvalidIdent { line } => *line,
       UnexpectedCharacter { line, .. } => *line,
       UnterminatedString { line } => *line,
+      UnterminatedBlockComment { line } => *line,
       UnknownPrefix { line } => *line,
+      EmptyDigits { line, .. } => *line,
+      InvalidDigit { line, .. } => *line,
+      EmptyExponent { line } => *line,
+      TrailingSeparator { line, .. } => *line,
+      InvalidEscape { line, .. } => *line,
     }
   }
 }
@@ -160,15 +222,23 @@ use TokenizationError::*;
 /// Enum representing the literal types supported by the tokenizer.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LiteralKind {
-  /// `123`, `123.12` (all treated as `f64`).
+  /// `123`, `123.12`, `1e9`, `0xff` (all treated as `f64`).
   Number,
-  /// `"abc"`, `"abc`
+  /// `"abc"`, `"a\tb"`, could span multiple lines.
   Str,
+  /// `"Hello ${`, the first segment of an interpolated string.
+  StrHead,
+  /// `}, ${`, a segment between two interpolations.
+  StrMiddle,
+  /// `}!"`, the last segment of an interpolated string.
+  StrTail,
 }
 
 use LiteralKind::*;
 
 /// Base of numeric literal encoding according to its prefix.
+///
+/// Prefixes are case-insensitive, e.g. both `0x` and `0X` mean [Base::Hexadecimal].
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(u8)]
 pub enum Base {
@@ -182,11 +252,38 @@ pub enum Base {
   Hexadecimal = 16,
 }
 
+impl Base {
+  /// The (lower-case) prefix of literals in this base, empty for [Base::Decimal].
+  pub fn prefix(&self) -> &'static str {
+    match self {
+      Base::Binary => "0b",
+      Base::Octal => "0o",
+      Base::Decimal => "",
+      Base::Hexadecimal => "0x",
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Base::Binary => "binary",
+      Base::Octal => "octal",
+      Base::Decimal => "decimal",
+      Base::Hexadecimal => "hexadecimal",
+    }
+  }
+}
+
 /// Creates an iterator that produces tokens from the input string.
 ///
 /// Note that `EOF` won't be produced by this iterator.
 pub fn tokenize(input: &str) -> impl Iterator<Item = TagToken> + '_ {
-  let mut cursor = Cursor::new(input);
+  tokenize_at(input, 0, 1)
+}
+
+/// Like [tokenize], but `input` starts at byte offset `pos` and `line` of the whole source,
+/// so that spans of the tokens are relative to the whole source.
+pub fn tokenize_at(input: &str, pos: usize, line: usize) -> impl Iterator<Item = TagToken> + '_ {
+  let mut cursor = Cursor::new_at(input, pos, line);
   std::iter::from_fn(move || {
     let token = cursor.advance_token();
     if token.kind != TokenKind::Eof {
@@ -245,7 +342,7 @@ impl Cursor<'_> {
   pub fn advance_token(&mut self) -> TagToken {
     let first_char = match self.bump() {
       Some(c) => c,
-      None => return TagToken::new(TokenKind::Eof, 0, self.line()),
+      None => return self.finish_token(Eof),
     };
 
     let token_kind = match first_char {
@@ -256,8 +353,25 @@ impl Cursor<'_> {
 
       '/' => match self.first() {
         '/' => self.line_comment(),
+        '*' => self.block_comment(),
+        '=' => self.slash_equal(),
         _ => Slash,
       },
+      '-' => match self.first() {
+        '=' => self.minus_equal(),
+        '-' => self.minus_minus(),
+        _ => Minus,
+      },
+      '+' => match self.first() {
+        '=' => self.plus_equal(),
+        '+' => self.plus_plus(),
+        _ => Plus,
+      },
+      '*' => match self.first() {
+        '=' => self.star_equal(),
+        '*' => self.star_star(),
+        _ => Star,
+      },
       '=' => match self.first() {
         '=' => self.equal_equal(),
         _ => Eq,
@@ -279,15 +393,31 @@ impl Cursor<'_> {
       ';' => Semi,
       ',' => Comma,
       '.' => Dot,
+      ':' => Colon,
+      '?' => Question,
+      '%' => Percent,
       '(' => OpenParen,
       ')' => CloseParen,
-      '{' => OpenBrace,
-      '}' => CloseBrace,
+      '{' => {
+        if let Some(depth) = self.interpolations.last_mut() {
+          *depth += 1;
+        }
+        OpenBrace
+      }
+      // The `}` closing an interpolation resumes the string.
+      '}' => match self.interpolations.last_mut() {
+        Some(0) => {
+          self.interpolations.pop();
+          self.double_quoted_string(false)
+        }
+        Some(depth) => {
+          *depth -= 1;
+          CloseBrace
+        }
+        None => CloseBrace,
+      },
       '[' => OpenBracket,
       ']' => CloseBracket,
-      '-' => Minus,
-      '+' => Plus,
-      '*' => Star,
 
       // Whitespace (`new_line` is not included here)
       c if is_whitespace(c) => self.whitespace(),
@@ -297,32 +427,27 @@ impl Cursor<'_> {
       c if is_id_start(c) => self.ident_or_unknown_prefix(),
 
       // Numeric Literal
-      c @ '0'..='9' => {
-        let literal_kind = self.number(c);
-        Literal { kind: literal_kind }
-      }
+      c @ '0'..='9' => self.number(c),
 
       // String Literal, will take `"` into account of `str_len`
-      '"' => {
-        let terminated = self.double_quoted_string();
-        let str_len = self.pos_within_token();
-        if !terminated {
-          return TagToken::new(
-            TokErr(UnterminatedString { line: self.line() }),
-            str_len,
-            self.line(),
-          );
-        }
-        let kind = Str;
-        Literal { kind }
-      }
+      '"' => self.double_quoted_string(true),
 
       _ => TokErr(UnexpectedCharacter {
         ch: first_char,
         line: self.line(),
       }),
     };
-    let res = TagToken::new(token_kind, self.pos_within_token(), self.line());
+    self.finish_token(token_kind)
+  }
+
+  /// Wraps the consumed characters into a token of `kind`.
+  fn finish_token(&mut self, kind: TokenKind) -> TagToken {
+    let res = TagToken::new(
+      kind,
+      self.pos_within_token(),
+      self.line(),
+      self.token_span(),
+    );
     // Remember to reset the consumed bytes length!
     self.reset_pos_within_token();
     res
@@ -344,24 +469,113 @@ impl Cursor<'_> {
     LineComment
   }
 
-  /// Eats double-quoted string and returns true
-  /// if string is terminated.
-  fn double_quoted_string(&mut self) -> bool {
-    debug_assert!(self.prev() == '"');
+  fn block_comment(&mut self) -> TokenKind {
+    debug_assert!(self.prev() == '/' && self.first() == '*');
+    self.bump(); // Eat `*`.
+
+    let mut depth = 1usize;
     while let Some(c) = self.bump() {
       match c {
-        '"' => {
-          return true;
+        '/' if self.first() == '*' => {
+          self.bump();
+          depth += 1;
         }
-        '\\' if self.first() == '\\' || self.first() == '"' => {
-          // Bump again to skip escaped character.
+        '*' if self.first() == '/' => {
           self.bump();
+          depth -= 1;
+          if depth == 0 {
+            return BlockComment;
+          }
         }
-        _ => {}
+        BACKSLASH_N => *self.line_mut() += 1,
+        _ => (),
       }
     }
     // End of file reached.
-    false
+    TokErr(UnterminatedBlockComment { line: self.line() })
+  }
+
+  /// Eats a double-quoted string (or a segment of it), whose escapes are checked but not decoded.
+  ///
+  /// `is_head` tells if it starts with `"` rather than a `}` closing an interpolation.
+  /// The segment ends at the closing `"` or a `${`.
+  ///
+  /// An unterminated string is reported rather than its invalid escapes.
+  fn double_quoted_string(&mut self, is_head: bool) -> TokenKind {
+    let mut invalid_escape = None;
+    while let Some(c) = self.bump() {
+      let kind = match c {
+        '"' if is_head => Str,
+        '"' => StrTail,
+        '$' if self.first() == '{' => {
+          self.bump(); // Eat `{`.
+          self.interpolations.push(0);
+          if is_head {
+            StrHead
+          } else {
+            StrMiddle
+          }
+        }
+        // Strings could span multiple lines.
+        BACKSLASH_N => {
+          *self.line_mut() += 1;
+          continue;
+        }
+        '\\' => {
+          if let Err(ch) = self.escape() {
+            let line = self.line();
+            invalid_escape.get_or_insert(InvalidEscape { ch, line });
+          }
+          continue;
+        }
+        _ => continue,
+      };
+      return match invalid_escape {
+        Some(error) => TokErr(error),
+        None => Literal { kind },
+      };
+    }
+    // End of file reached.
+    TokErr(UnterminatedString { line: self.line() })
+  }
+
+  /// Eats an escape after `\`, returns the character after `\` if it's invalid.
+  fn escape(&mut self) -> Result<(), char> {
+    match self.first() {
+      'n' | 't' | 'r' | '0' | '\\' | '"' | '$' => {
+        self.bump();
+        Ok(())
+      }
+      'u' => {
+        self.bump();
+        if self.unicode_escape() {
+          Ok(())
+        } else {
+          Err('u')
+        }
+      }
+      // Not eaten, e.g. it may be the closing `"`.
+      c => Err(c),
+    }
+  }
+
+  /// Eats `{XXXX}` after `\u`, and returns true if it's 1 to 6 hex digits of a valid `char`.
+  fn unicode_escape(&mut self) -> bool {
+    if self.first() != '{' {
+      return false;
+    }
+    self.bump();
+    let (mut code, mut len) = (0u32, 0);
+    while let Some(digit) = self.first().to_digit(16) {
+      self.bump();
+      code = code.saturating_mul(16).saturating_add(digit);
+      len += 1;
+    }
+    if self.first() != '}' {
+      return false;
+    }
+    self.bump();
+    (1..=6).contains(&len) && char::from_u32(code).is_some()
   }
 
   fn ident_or_unknown_prefix(&mut self) -> TokenKind {
@@ -390,41 +604,110 @@ impl Cursor<'_> {
     TokErr(InvalidIdent { line: self.line() })
   }
 
-  fn number(&mut self, _first_digit: char) -> LiteralKind {
+  /// Eats a numeric literal, which is one of
+  ///
+  /// - `0b`, `0o` or `0x` (in any case) followed by digits of the [Base],
+  /// - decimal digits, with an optional fraction and exponent, e.g. `2.5E-3`.
+  ///
+  /// The `_` separator could appear among digits, but not at the end of them.
+  fn number(&mut self, first_digit: char) -> TokenKind {
     debug_assert!('0' <= self.prev() && self.prev() <= '9');
 
-    let base = Base::Decimal;
+    let base = match (first_digit, self.first()) {
+      ('0', 'b' | 'B') => Base::Binary,
+      ('0', 'o' | 'O') => Base::Octal,
+      ('0', 'x' | 'X') => Base::Hexadecimal,
+      _ => Base::Decimal,
+    };
+    if base != Base::Decimal {
+      self.bump(); // Eat the prefix.
+      return self.prefixed_digits(base);
+    }
 
     // Eat integer part
-    self.eat_decimal_digits();
+    let (_, mut trailing_separator) = self.eat_decimal_digits();
+    // Keep eating the literal even after a trailing `_`, so that it's reported as a whole.
 
     // Make sure the pattern is `<numeric>.<numeric>`
-    if self.first() == '.' && self.second().is_digit(base as u32) {
+    if self.first() == '.' && self.second().is_ascii_digit() {
       self.bump(); // Eat `.`
-      self.eat_decimal_digits();
+      trailing_separator |= self.eat_decimal_digits().1;
+    }
+
+    if matches!(self.first(), 'e' | 'E') {
+      self.bump(); // Eat `e`
+      if matches!(self.first(), '+' | '-') {
+        self.bump();
+      }
+      let (has_digits, trailing) = self.eat_decimal_digits();
+      if !has_digits {
+        return TokErr(EmptyExponent { line: self.line() });
+      }
+      trailing_separator |= trailing;
+    }
+
+    if trailing_separator {
+      return TokErr(TrailingSeparator {
+        base,
+        line: self.line(),
+      });
+    }
+    Literal { kind: Number }
+  }
+
+  /// Eats the digits after the prefix of `base`.
+  ///
+  /// Like `rustc`, all alphanumeric characters are eaten, so that an invalid digit
+  /// (e.g. `0b102`) is reported as a part of the literal.
+  fn prefixed_digits(&mut self, base: Base) -> TokenKind {
+    let mut has_digits = false;
+    let mut trailing_separator = false;
+    let mut invalid = None;
+    loop {
+      match self.first() {
+        '_' => {}
+        c if c.is_digit(base as u32) => has_digits = true,
+        c if c.is_ascii_alphanumeric() => {
+          invalid.get_or_insert(c);
+        }
+        _ => break,
+      }
+      trailing_separator = self.first() == '_';
+      self.bump();
     }
 
-    Number
+    let line = self.line();
+    match invalid {
+      Some(ch) => TokErr(InvalidDigit { ch, base, line }),
+      None if !has_digits => TokErr(EmptyDigits { base, line }),
+      None if trailing_separator => TokErr(TrailingSeparator { base, line }),
+      None => Literal { kind: Number },
+    }
   }
 
   /// `_` could appear in the sequence of numeric literals.
   ///
   /// E.g. `1_000_000`, `100_000.123_456`
-  fn eat_decimal_digits(&mut self) -> bool {
+  ///
+  /// Returns whether any digit was eaten, and whether the eaten sequence ends with `_`.
+  fn eat_decimal_digits(&mut self) -> (bool, bool) {
     let mut has_digits = false;
+    let mut trailing_separator = false;
     loop {
       match self.first() {
         '_' => {
+          trailing_separator = true;
           self.bump();
         }
         '0'..='9' => {
           has_digits = true;
+          trailing_separator = false;
           self.bump();
         }
         _ => break,
       }
     }
-    has_digits
+    (has_digits, trailing_separator)
   }
 }
 
@@ -435,6 +718,48 @@ impl Cursor<'_> {
     EqEq
   }
 
+  fn minus_equal(&mut self) -> TokenKind {
+    debug_assert!(self.prev() == '-' && self.first() == '=');
+    self.bump(); // Eat `=`.
+    MinusEq
+  }
+
+  fn plus_equal(&mut self) -> TokenKind {
+    debug_assert!(self.prev() == '+' && self.first() == '=');
+    self.bump(); // Eat `=`.
+    PlusEq
+  }
+
+  fn star_equal(&mut self) -> TokenKind {
+    debug_assert!(self.prev() == '*' && self.first() == '=');
+    self.bump(); // Eat `=`.
+    StarEq
+  }
+
+  fn slash_equal(&mut self) -> TokenKind {
+    debug_assert!(self.prev() == '/' && self.first() == '=');
+    self.bump(); // Eat `=`.
+    SlashEq
+  }
+
+  fn star_star(&mut self) -> TokenKind {
+    debug_assert!(self.prev() == '*' && self.first() == '*');
+    self.bump(); // Eat `*`.
+    StarStar
+  }
+
+  fn minus_minus(&mut self) -> TokenKind {
+    debug_assert!(self.prev() == '-' && self.first() == '-');
+    self.bump(); // Eat `-`.
+    MinusMinus
+  }
+
+  fn plus_plus(&mut self) -> TokenKind {
+    debug_assert!(self.prev() == '+' && self.first() == '+');
+    self.bump(); // Eat `+`.
+    PlusPlus
+  }
+
   fn bang_equal(&mut self) -> TokenKind {
     debug_assert!(self.prev() == '!' && self.first() == '=');
     self.bump(); // Eat `=`.
@@ -487,6 +812,8 @@ impl TagToken {
       Semi => "SEMICOLON ;",
       Dot => "DOT .",
       Comma => "COMMA ,",
+      Colon => "COLON :",
+      Question => "QUESTION ?",
 
       Eq => "EQUAL =",
       EqEq => "EQUAL_EQUAL ==",
@@ -501,6 +828,14 @@ impl TagToken {
       Plus => "PLUS +",
       Star => "STAR *",
       Slash => "SLASH /",
+      Percent => "PERCENT %",
+      StarStar => "STAR_STAR **",
+      MinusEq => "MINUS_EQUAL -=",
+      PlusEq => "PLUS_EQUAL +=",
+      StarEq => "STAR_EQUAL *=",
+      SlashEq => "SLASH_EQUAL /=",
+      MinusMinus => "MINUS_MINUS --",
+      PlusPlus => "PLUS_PLUS ++",
 
       Eof => "EOF ",
       _ => "",
@@ -0,0 +1,39 @@
+/// A region of the source text.
+///
+/// `start` and `end` are byte offsets (`end` is exclusive),
+/// `line` and `col` (counted in chars) are both `1-based`, and point to `start`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+  pub start: usize,
+  pub end: usize,
+  pub line: usize,
+  pub col: usize,
+}
+
+impl Span {
+  pub fn new(start: usize, end: usize, line: usize, col: usize) -> Self {
+    Self {
+      start,
+      end,
+      line,
+      col,
+    }
+  }
+
+  /// Length in bytes.
+  pub fn len(&self) -> usize {
+    self.end - self.start
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.start == self.end
+  }
+
+  /// A span covering from the start of `self` to the end of `other`.
+  pub fn to(self, other: Span) -> Span {
+    Span {
+      end: other.end.max(self.end),
+      ..self
+    }
+  }
+}
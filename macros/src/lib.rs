@@ -23,6 +23,7 @@ pub fn gen_reserved_tok_methods(input: TokenStream) -> TokenStream {
                 kind: Identifier,
                 len: #len,
                 line: 0,
+                span: Span::default(),
               },
               val: #word,
             }
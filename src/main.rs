@@ -1,23 +1,69 @@
 use ember_lox_ast::visit::VisitorAcceptor;
 use ember_lox_ast::AstPrinter;
+use ember_lox_parse::error::{Diagnostic, ParseError, RenderMode, Renderer};
 use ember_lox_parse::prelude::*;
-use ember_lox_rt::ast_interpreter::Interpreter;
+use ember_lox_rt::resolver::Resolver;
+use ember_lox_rt::runtime::Backend;
+use ember_lox_rt::stdlib;
 use std::env;
 use std::fs;
 
+mod repl;
+
 const TEST_MODE: bool = false;
 const TEST_CMD: &str = "evaluate";
 const TEST_FILENAME: &str = "test.lox";
 
 fn main() {
-  let args: Vec<String> = env::args().collect();
+  // Options (`--key=value`) could be placed anywhere, the rest are positional.
+  let (options, args): (Vec<String>, Vec<String>) =
+    env::args().partition(|arg| arg.starts_with("--"));
+  let mut backend = Backend::default();
+  let mut mode = RenderMode::default();
+  let mut with_stdlib = true;
+  for option in &options {
+    let parsed = match option.split_once('=') {
+      Some(("--backend", value)) => value.parse().map(|b| backend = b),
+      Some(("--diagnostics", value)) => value.parse().map(|m| mode = m),
+      Some(("--stdlib", value @ ("on" | "off"))) => {
+        with_stdlib = value == "on";
+        Ok(())
+      }
+      _ => Err(format!("Unknown option: {}", option)),
+    };
+    if let Err(msg) = parsed {
+      eprintln!("{}", msg);
+      std::process::exit(64)
+    }
+  }
+
+  let mut runtime = backend.runtime();
+  if with_stdlib {
+    stdlib::natives()
+      .into_iter()
+      .for_each(|native| runtime.define_native(native));
+  }
+
+  // Without a command, start the `REPL`.
+  if !TEST_MODE && (args.len() == 1 || args[1] == "repl") {
+    repl::run_repl(runtime, mode);
+    return;
+  }
   if !TEST_MODE && args.len() < 3 {
-    eprintln!("Usage: <loxc-path> <Commands> <filename>\n");
+    eprintln!("Usage: <loxc-path> <Commands> <filename> [Options]");
+    eprintln!("       <loxc-path> [repl] [Options]\n");
     eprintln!("Commands:");
     eprintln!("  tokenize   - Tokenize the source code");
     eprintln!("  parse      - Parse the source code");
     eprintln!("  run        - Run the source code");
     eprintln!("  evaluate   - Evaluate the source code");
+    eprintln!("  repl       - Start an interactive session (default)\n");
+    eprintln!("Options:");
+    eprintln!("  --backend=<tree|vm>            - Backend to run with (default: tree)");
+    eprintln!("  --diagnostics=<rich|compat>    - How errors are shown (default: rich)");
+    eprintln!(
+      "  --stdlib=<on|off>              - Whether to define the standard library (default: on)"
+    );
     return;
   }
 
@@ -45,6 +91,8 @@ fn main() {
     src.push(';');
   }
 
+  let renderer = Renderer::new(&src, filename, mode);
+
   match command {
     "tokenize" => {
       let mut pure_tokens = vec![];
@@ -68,7 +116,10 @@ fn main() {
       last_line = last_line.max(tok_errors.last().map(|e| e.tag.line).unwrap_or_default());
       pure_tokens.push(Token::eof_tok(last_line));
 
-      tok_errors.iter().for_each(|e| eprintln!("{}", e.dbg()));
+      tok_errors
+        .iter()
+        .filter_map(Token::lexical_error)
+        .for_each(|e| renderer.emit(&(&e).into()));
       pure_tokens
         .iter()
         .filter(|t| !t.dbg().is_empty())
@@ -78,9 +129,10 @@ fn main() {
     }
     "parse" => {
       let mut parser = new_parser_from_src_str(&src);
-      let Some(asts) = parser.parse() else {
+      let asts = parser.parse().unwrap_or_else(|errors| {
+        emit_parse_errors(&renderer, &errors);
         std::process::exit(65)
-      };
+      });
       let mut printer = AstPrinter;
       asts
         .into_iter()
@@ -88,15 +140,27 @@ fn main() {
     }
     c if matches!(c, "run" | "evaluate") => {
       let mut parser = new_parser_from_src_str(&src);
-      let Some(asts) = parser.parse() else {
+      let asts = parser.parse().unwrap_or_else(|errors| {
+        emit_parse_errors(&renderer, &errors);
         std::process::exit(65)
-      };
-      let mut interpreter = Interpreter::default();
+      });
+      if let Err(diagnostics) = Resolver::default().resolve(&asts) {
+        renderer.emit_all(&diagnostics);
+        std::process::exit(65)
+      }
       let repl_mode = c == "evaluate";
-      if interpreter.interpret(&asts, repl_mode).is_err() {
+      if let Err(error) = runtime.interpret(&asts, repl_mode) {
+        renderer.emit(&Diagnostic::from(&error));
         std::process::exit(70)
       }
     }
     _ => eprintln!("Unknown command: {}", command),
   }
 }
+
+/// Parse errors are only printed here (and in the `REPL`).
+pub(crate) fn emit_parse_errors(renderer: &Renderer, errors: &[ParseError]) {
+  errors
+    .iter()
+    .for_each(|e| renderer.emit(&Diagnostic::from(e)));
+}
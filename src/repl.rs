@@ -0,0 +1,144 @@
+//! An interactive `REPL`, which keeps one runtime alive across inputs.
+
+use ember_lox_parse::error::{Diagnostic, RenderMode, Renderer};
+use ember_lox_parse::prelude::*;
+use ember_lox_rt::resolver::Resolver;
+use ember_lox_rt::runtime::Runtime;
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = ". ";
+
+/// Name of the session in diagnostics.
+const SOURCE_NAME: &str = "<repl>";
+
+pub fn run_repl(mut runtime: Box<dyn Runtime>, mode: RenderMode) {
+  let mut editor = match DefaultEditor::new() {
+    Ok(editor) => editor,
+    Err(e) => {
+      eprintln!("Failed to start the REPL: {}", e);
+      std::process::exit(74)
+    }
+  };
+  let mut src = String::new();
+  // All inputs so far, so that diagnostics could point into earlier lines.
+  let mut session = String::new();
+
+  loop {
+    let prompt = if src.is_empty() {
+      PROMPT
+    } else {
+      CONTINUATION_PROMPT
+    };
+    match editor.readline(prompt) {
+      Ok(line) => {
+        src.push_str(&line);
+        src.push('\n');
+        // Keep reading, until all braces and parentheses are closed.
+        if !is_balanced(&src) {
+          continue;
+        }
+      }
+      // `Ctrl-C` discards the pending input.
+      Err(ReadlineError::Interrupted) => {
+        src.clear();
+        continue;
+      }
+      Err(ReadlineError::Eof) => break,
+      Err(e) => {
+        eprintln!("{}", e);
+        break;
+      }
+    }
+
+    let input = std::mem::take(&mut src);
+    let input = input.trim();
+    if input.is_empty() {
+      continue;
+    }
+    editor.add_history_entry(input).ok();
+
+    let mut input = input.to_string();
+    // Allow the user to omit the trailing semicolon.
+    if !input.ends_with(';') && !input.ends_with('}') {
+      input.push(';');
+    }
+    let (pos, line) = (session.len(), session.lines().count() + 1);
+    session.push_str(&input);
+    session.push('\n');
+    let renderer = Renderer::new(&session, SOURCE_NAME, mode);
+
+    // Errors are reported, and definitions made so far are kept.
+    let mut parser = new_parser_at(&session[pos..], pos, line);
+    let asts = match parser.parse() {
+      Ok(asts) => asts,
+      Err(errors) => {
+        crate::emit_parse_errors(&renderer, &errors);
+        continue;
+      }
+    };
+    if let Err(diagnostics) = Resolver::default().resolve(&asts) {
+      renderer.emit_all(&diagnostics);
+      continue;
+    }
+    if let Err(error) = runtime.interpret(&asts, true) {
+      renderer.emit(&Diagnostic::from(&error));
+    }
+  }
+}
+
+/// Checks if all `(`, `[` and `{` in `src` are closed, ignoring strings and comments.
+///
+/// Code inside `${ ... }` of a string is checked as well, so it may contain strings.
+fn is_balanced(src: &str) -> bool {
+  let mut depth = 0isize;
+  let mut in_string = false;
+  // The `depth` outside of each enclosing `${`, innermost last.
+  let mut interpolations = vec![];
+  let mut comment_depth = 0usize;
+  let mut chars = src.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '"' => in_string = !in_string,
+      // Skip the escaped one, which may be a `"` or `$`.
+      '\\' if in_string => {
+        chars.next();
+      }
+      '$' if in_string && chars.peek() == Some(&'{') => {
+        chars.next();
+        interpolations.push(depth);
+        depth += 1;
+        in_string = false;
+      }
+      _ if in_string => {}
+      '/' if chars.peek() == Some(&'/') => {
+        chars.find(|&c| c == '\n');
+      }
+      '/' if chars.peek() == Some(&'*') => {
+        chars.next();
+        comment_depth += 1;
+        while comment_depth > 0 {
+          match (chars.next(), chars.peek()) {
+            (Some('/'), Some('*')) => comment_depth += 1,
+            (Some('*'), Some('/')) => comment_depth -= 1,
+            (Some(_), _) => continue,
+            (None, _) => return false,
+          }
+          chars.next();
+        }
+      }
+      '(' | '[' | '{' => depth += 1,
+      ')' | ']' | '}' => {
+        depth -= 1;
+        // The `}` of an interpolation resumes its string.
+        if c == '}' && interpolations.last() == Some(&depth) {
+          interpolations.pop();
+          in_string = true;
+        }
+      }
+      _ => {}
+    }
+  }
+  // Extra closing ones are left to the parser to report.
+  !in_string && interpolations.is_empty() && depth <= 0
+}
@@ -2,32 +2,44 @@
 pub mod ast_printer_test {
   use ember_lox_ast::{ast::prelude::*, visit::VisitorAcceptor, AstPrinter};
   use ember_lox_rt::prelude::*;
+  use ember_lox_tokenizer::Span;
+
+  /// A span on the first line, `col` is `1-based`.
+  fn span(col: usize, len: usize) -> Span {
+    Span::new(col - 1, col - 1 + len, 1, col)
+  }
 
   #[test]
   fn case1() {
     // var x = 1 + 2 * 3
     let expr = Stmt::Variable {
-      name: (intern_string("x"), 1).into(),
+      name: (intern_string("x"), span(5, 1)).into(),
       initializer: Expr::Binary {
         left: Expr::Literal {
-          val: (1.0.into(), 1).into(),
+          val: (1.0.into(), span(9, 1)).into(),
+          span: span(9, 1),
         }
         .into(),
-        op: (Operator::Plus, 1).into(),
+        op: (Operator::Plus, span(11, 1)).into(),
         right: Expr::Binary {
           left: Expr::Literal {
-            val: (2.0.into(), 1).into(),
+            val: (2.0.into(), span(13, 1)).into(),
+            span: span(13, 1),
           }
           .into(),
-          op: (Operator::Multiply, 1).into(),
+          op: (Operator::Multiply, span(15, 1)).into(),
           right: Expr::Literal {
-            val: (3.0.into(), 1).into(),
+            val: (3.0.into(), span(17, 1)).into(),
+            span: span(17, 1),
           }
           .into(),
+          span: span(13, 5),
         }
         .into(),
+        span: span(9, 9),
       }
       .into(),
+      span: span(1, 17),
     };
 
     let mut printer = AstPrinter;
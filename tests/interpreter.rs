@@ -0,0 +1,530 @@
+use std::{fs, process::Command};
+
+const BACKENDS: [&str; 2] = ["tree", "vm"];
+
+/// Runs `src` with the given command and extra `options`, returns `(stdout, stderr, exit_code)`.
+fn run_lox_with(command: &str, name: &str, src: &str, options: &[&str]) -> (String, String, i32) {
+  let path = std::env::temp_dir().join(format!("ember-lox-{}.lox", name));
+  fs::write(&path, src).unwrap();
+  let output = Command::new(env!("CARGO_BIN_EXE_ember-lox"))
+    .arg(command)
+    .arg(&path)
+    // The given `options` come later, so they take precedence.
+    .arg("--diagnostics=compat")
+    .args(options)
+    .output()
+    .unwrap();
+  fs::remove_file(&path).ok();
+  (
+    String::from_utf8(output.stdout).unwrap(),
+    String::from_utf8(output.stderr).unwrap(),
+    output.status.code().unwrap_or(-1),
+  )
+}
+
+/// Runs `src` on every backend, asserts they behave the same, and returns the result.
+fn run_lox(command: &str, name: &str, src: &str) -> (String, String, i32) {
+  let results = BACKENDS.map(|backend| {
+    let name = format!("{}-{}", name, backend);
+    run_lox_with(command, &name, src, &[&format!("--backend={}", backend)])
+  });
+  assert_eq!(results[0], results[1], "backends disagree on `{}`", name);
+  results[0].clone()
+}
+
+#[test]
+fn test_recursion_and_closures() {
+  let src = r#"
+    fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+    fun makeCounter() {
+      var i = 0;
+      fun count() { i = i + 1; return i; }
+      return count;
+    }
+    var counter = makeCounter();
+    print fib(10);
+    print counter();
+    print counter();
+    print counter;
+  "#;
+  let (stdout, _, code) = run_lox("run", "closures", src);
+  assert_eq!(stdout, "55\n1\n2\n<fn count>\n");
+  assert_eq!(code, 0);
+}
+
+#[test]
+fn test_arity_mismatch() {
+  let (_, stderr, code) = run_lox("run", "arity", "fun f(a) {}\nf(1, 2);");
+  assert_eq!(stderr, "Expected 1 arguments but got 2.\n[line 2]\n");
+  assert_eq!(code, 70);
+}
+
+#[test]
+fn test_classes_and_inheritance() {
+  let src = r#"
+    class A {
+      init(name) { this.name = name; }
+      greet() { return "hi " + this.name; }
+    }
+    class B < A {
+      greet() { return super.greet() + "!"; }
+    }
+    var b = B("lox");
+    print b.greet();
+    print b;
+    print b.init("again").name;
+  "#;
+  let (stdout, _, code) = run_lox("run", "classes", src);
+  assert_eq!(stdout, "hi lox!\nB instance\nagain\n");
+  assert_eq!(code, 0);
+}
+
+#[test]
+fn test_bound_method_equality() {
+  let src = r#"
+    class A { m() {} n() {} }
+    class B < A {}
+    var a = A();
+    var b = B();
+    var m = a.m;
+    print a.m == a.m;
+    print m == m;
+    print b.m == b.m;
+    print a.m == a.n;
+    print a.m == b.m;
+  "#;
+  let (stdout, _, code) = run_lox("run", "bound-method-equality", src);
+  assert_eq!(stdout, "true\ntrue\ntrue\nfalse\nfalse\n");
+  assert_eq!(code, 0);
+}
+
+#[test]
+fn test_superclass_must_be_a_class() {
+  let (_, stderr, code) = run_lox("run", "superclass", "var x = 1;\nclass A < x {}");
+  assert_eq!(stderr, "Superclass must be a class.\n[line 2]\n");
+  assert_eq!(code, 70);
+}
+
+#[test]
+fn test_closure_binding_is_static() {
+  let src = r#"
+    var a = "global";
+    {
+      fun showA() { print a; }
+      showA();
+      var a = "block";
+      showA();
+    }
+  "#;
+  let (stdout, _, code) = run_lox("run", "static-scope", src);
+  assert_eq!(stdout, "global\nglobal\n");
+  assert_eq!(code, 0);
+}
+
+#[test]
+fn test_resolver_errors() {
+  let (_, stderr, code) = run_lox("run", "resolver", "{\n  var a = 1;\n  var a = a;\n}");
+  assert_eq!(
+    stderr,
+    "[line 3] Error at 'a': Already a variable with this name in this scope.\n\
+     [line 3] Error at 'a': Can't read local variable in its own initializer.\n"
+  );
+  assert_eq!(code, 65);
+
+  let src = "fun f() { return 1; }\nreturn f();";
+  let (_, stderr, code) = run_lox("run", "resolver-return", src);
+  assert_eq!(
+    stderr,
+    "[line 2] Error at 'return': Can't return from top-level code.\n"
+  );
+  assert_eq!(code, 65);
+}
+
+#[test]
+fn test_backend_option() {
+  let src = "fun f(n) { return n * 2; }\nprint f(21);";
+  let (stdout, _, code) = run_lox_with("run", "backend-default", src, &[]);
+  assert_eq!((stdout.as_str(), code), ("42\n", 0));
+
+  // Options could also precede the command.
+  let path = std::env::temp_dir().join("ember-lox-backend-first.lox");
+  fs::write(&path, src).unwrap();
+  let output = Command::new(env!("CARGO_BIN_EXE_ember-lox"))
+    .arg("--backend=vm")
+    .arg("run")
+    .arg(&path)
+    .output()
+    .unwrap();
+  fs::remove_file(&path).ok();
+  assert_eq!(String::from_utf8(output.stdout).unwrap(), "42\n");
+
+  let (_, stderr, code) = run_lox_with("run", "backend-unknown", src, &["--backend=jit"]);
+  assert_eq!((stderr.as_str(), code), ("Unknown backend: jit\n", 64));
+}
+
+#[test]
+fn test_rich_diagnostics() {
+  let src = "var a = 1;\nprint a + \"é\";";
+  let path = std::env::temp_dir().join("ember-lox-rich-runtime.lox");
+  for backend in BACKENDS {
+    let options = [&format!("--backend={}", backend), "--diagnostics=rich"];
+    let (_, stderr, code) = run_lox_with("run", "rich-runtime", src, &options);
+    let expected = format!(
+      "error: Operands must be numbers.\n --> {}:2:9\n  |\n2 | print a + \"é\";\n  |         ^\n",
+      path.display()
+    );
+    assert_eq!(stderr, expected);
+    assert_eq!(code, 70);
+  }
+
+  // Lexical errors underline the whole token, up to the end of line.
+  let src = "(1 +\n  \"abc) @";
+  let (_, stderr, code) = run_lox_with("parse", "rich-parse", src, &["--diagnostics=rich"]);
+  assert!(stderr.contains("error: Unterminated string.\n"));
+  assert!(stderr.contains("2 |   \"abc) @;\n  |   ^^^^^^^^\n"));
+  assert_eq!(code, 65);
+}
+
+#[test]
+fn test_rich_labels_and_notes() {
+  let src = "{ var a = 1; var a = 2; }\nclass A {\n  init() { return 1; }\n}";
+  let path = std::env::temp_dir().join("ember-lox-rich-resolver.lox");
+  let (_, stderr, code) = run_lox_with("run", "rich-resolver", src, &["--diagnostics=rich"]);
+  let expected = format!(
+    "error: Already a variable with this name in this scope.\n --> {0}:1:18\n  |\n\
+     1 | {{ var a = 1; var a = 2; }}\n  |       - previously declared here\n  |                  ^\n\
+     error: Can't return a value from an initializer.\n --> {0}:3:12\n  |\n\
+     3 |   init() {{ return 1; }}\n  |            ^^^^^^\n  = note: an initializer always returns 'this'\n",
+    path.display()
+  );
+  assert_eq!(stderr, expected);
+  assert_eq!(code, 65);
+}
+
+#[test]
+fn test_stack_overflow() {
+  let src = "fun f(n) { if (n == 0) return 0; return f(n - 1) + 1; }\nprint f(1000);\nf(-1);";
+  let (stdout, stderr, code) = run_lox("run", "stack-overflow", src);
+  assert_eq!(stdout, "1000\n");
+  assert_eq!(stderr, "Stack overflow.\n[line 1]\n");
+  assert_eq!(code, 70);
+}
+
+#[test]
+fn test_stack_trace() {
+  // Long traces are shortened.
+  for backend in BACKENDS {
+    let options = [&format!("--backend={}", backend), "--diagnostics=rich"];
+    let (_, stderr, code) = run_lox_with("run", "stack-trace", "fun f() { f(); }\nf();", &options);
+    assert!(stderr.contains("  = stack trace:\n      [line 1] in f()\n"));
+    assert!(stderr.contains("      ... (1004 frames omitted)\n"));
+    assert!(stderr.ends_with("      [line 2] in script\n"));
+    assert_eq!(stderr.lines().count(), 5 + 1 + 21);
+    assert_eq!(code, 70);
+  }
+}
+
+#[test]
+fn test_stdlib() {
+  let src = r#"
+    print len("héllo");
+    print substr("hello", 1, 3);
+    print upper("a") + lower("B");
+    print num(" 2.5 ") + 1;
+    print str(1) + str(nil);
+    print type_of(nil);
+    print floor(2.7);
+    print pow(2, 10);
+    print max(1, 2);
+    seed(1);
+    var a = random();
+    seed(1);
+    print a == random();
+  "#;
+  let (stdout, _, code) = run_lox("run", "stdlib", src);
+  assert_eq!(stdout, "5\nel\nAb\n3.5\n1nil\nnil\n2\n1024\n2\ntrue\n");
+  assert_eq!(code, 0);
+
+  let (_, stderr, code) = run_lox("run", "stdlib-error", "print sqrt(\"x\");");
+  assert_eq!(
+    stderr,
+    "sqrt() expects a number but got string.\n[line 1]\n"
+  );
+  assert_eq!(code, 70);
+
+  // Only finite numbers could be written in Lox.
+  for s in ["inf", "-infinity", "NaN", "1e999"] {
+    let (_, stderr, code) = run_lox("run", "stdlib-num", &format!("print num(\"{}\");", s));
+    assert_eq!(
+      stderr,
+      format!("num() can't convert \"{}\" to a number.\n[line 1]\n", s)
+    );
+    assert_eq!(code, 70);
+  }
+
+  let (_, stderr, code) = run_lox_with("run", "stdlib-off", "print len(\"\");", &["--stdlib=off"]);
+  assert_eq!(stderr, "Undefined variable: 'len'.\n[line 1]\n");
+  assert_eq!(code, 70);
+}
+
+#[test]
+fn test_lists() {
+  let src = r#"
+    var xs = [1, "a", [true]];
+    var ys = xs;
+    ys[0] = xs[0] + 1;
+    push(ys, nil);
+    print xs;
+    print len(xs);
+    print pop(xs);
+    print xs[2][0];
+    print split("a,b", ",");
+    print xs == ys;
+    print [] == [];
+  "#;
+  let (stdout, _, code) = run_lox("run", "lists", src);
+  assert_eq!(
+    stdout,
+    "[2, \"a\", [true], nil]\n4\nnil\ntrue\n[\"a\", \"b\"]\ntrue\nfalse\n"
+  );
+  assert_eq!(code, 0);
+
+  let cases = [
+    (
+      "var xs = [1];\nprint xs[1];",
+      "Index 1 is out of bounds for length 1.",
+    ),
+    (
+      "var xs = [1];\nxs[0.5] = 1;",
+      "Index must be an integer but got 0.5.",
+    ),
+    (
+      "var xs = [1];\nprint xs[nil];",
+      "Index must be an integer but got nil.",
+    ),
+    (
+      "var x = 1;\nprint x[0];",
+      "Only lists and maps can be indexed.",
+    ),
+    ("pop([]);\n", "pop() expects a non-empty list."),
+  ];
+  for (src, message) in cases {
+    let (_, stderr, code) = run_lox("run", "list-errors", src);
+    assert!(stderr.starts_with(message), "{}", stderr);
+    assert_eq!(code, 70);
+  }
+}
+
+#[test]
+fn test_maps() {
+  let src = r#"
+    var m = {"a": 1, 2: [true], nil: "n"};
+    var alias = m;
+    alias["a"] = m["a"] + 1;
+    m[false] = {};
+    print m;
+    print len(m);
+    print keys(m);
+    print values({"x": 1, "x": 2});
+    print entries({"k": "v"});
+    print has(m, nil);
+    print remove(m, 2);
+    print has(m, 2);
+  "#;
+  let (stdout, _, code) = run_lox("run", "maps", src);
+  assert_eq!(
+    stdout,
+    concat!(
+      "{\"a\": 2, 2: [true], nil: \"n\", false: {}}\n",
+      "4\n[\"a\", 2, nil, false]\n[2]\n[[\"k\", \"v\"]]\ntrue\n[true]\nfalse\n"
+    )
+  );
+  assert_eq!(code, 0);
+
+  let cases = [
+    ("var m = {};\nprint m[\"x\"];", "Undefined key \"x\"."),
+    (
+      "var m = {[]: 1};",
+      "Map keys must be strings, numbers, booleans or nil but got list.",
+    ),
+    (
+      "var m = {};\nm[m] = 1;",
+      "Map keys must be strings, numbers, booleans or nil but got map.",
+    ),
+  ];
+  for (src, message) in cases {
+    let (_, stderr, code) = run_lox("run", "map-errors", src);
+    assert!(stderr.starts_with(message), "{}", stderr);
+    assert_eq!(code, 70);
+  }
+}
+
+#[test]
+fn test_numeric_literals() {
+  let src = "print 0x1F + 0b11 + 0o10 + 1_000;\nprint 2.5e-1;";
+  let (stdout, _, code) = run_lox("run", "numbers", src);
+  assert_eq!(stdout, "1042\n0.25\n");
+  assert_eq!(code, 0);
+
+  let (_, stderr, code) = run_lox(
+    "run",
+    "numbers-trailing",
+    "print 1_; print 0xff_; print 1_.5;",
+  );
+  assert!(stderr.contains("Trailing '_' in decimal literal 1_."));
+  assert!(stderr.contains("Trailing '_' in hexadecimal literal 0xff_."));
+  assert!(stderr.contains("Trailing '_' in decimal literal 1_.5."));
+  assert_eq!(code, 65);
+}
+
+#[test]
+fn test_string_interpolation() {
+  let src = r#"
+    var name = "world";
+    var n = 3;
+    fun f() { return "f"; }
+    print "Hello ${name}!";
+    print "${n} * 2 = ${n * 2}";
+    print "${"in${n}ner"} ${ {"k": [nil, true]}["k"] } ${f}";
+    print "\${name}";
+  "#;
+  let (stdout, _, code) = run_lox("run", "interpolation", src);
+  assert_eq!(
+    stdout,
+    "Hello world!\n3 * 2 = 6\nin3ner [nil, true] <fn f>\n${name}\n"
+  );
+  assert_eq!(code, 0);
+}
+
+#[test]
+fn test_break_and_continue() {
+  let src = r#"
+    for (var i = 0; i < 10; i = i + 1) {
+      if (i == 1) continue;
+      var square = i * i;
+      if (square > 10) break;
+      print square;
+    }
+    var closures = [];
+    var j = 0;
+    while (true) {
+      j = j + 1;
+      var k = j;
+      if (j == 2) continue;
+      fun get() { return k; }
+      closures = [get];
+      if (j == 3) { var inner = "leaving"; break; }
+    }
+    print closures[0]();
+    for (;;) {
+      for (var a = 0; ; a = a + 1) { if (a == 2) break; print a; }
+      break;
+    }
+  "#;
+  let (stdout, _, code) = run_lox("run", "loop-control", src);
+  assert_eq!(stdout, "0\n4\n9\n3\n0\n1\n");
+  assert_eq!(code, 0);
+}
+
+#[test]
+fn test_compound_assignment_and_increments() {
+  let src = r#"
+    var a = 1;
+    a += 2;
+    a *= 4;
+    a -= 2;
+    a /= 4;
+    print a;
+    print a++;
+    print ++a;
+    print a-- - --a;
+    var s = "a";
+    s += "b";
+    print s;
+
+    class Counter { init() { this.n = 0; } }
+    var counter = Counter();
+    var calls = 0;
+    fun get() { calls = calls + 1; return counter; }
+    get().n += 5;
+    print get().n++;
+    print counter.n;
+    var list = [1, 2];
+    var i = -1;
+    fun next() { i = i + 1; calls = calls + 1; return i; }
+    list[next()] *= 10;
+    print --list[next()];
+    print list;
+    print calls;
+    {
+      var captured = 1;
+      fun peek() { return captured; }
+      captured++;
+      print peek();
+    }
+  "#;
+  let (stdout, _, code) = run_lox("run", "updates", src);
+  assert_eq!(stdout, "2.5\n2.5\n4.5\n2\nab\n5\n6\n1\n[10, 1]\n4\n2\n");
+  assert_eq!(code, 0);
+
+  // Plain Lox, where `--` is two negations.
+  let src = "print --(3);\nprint --1;\nprint 1--1;\nvar a = 5;\nvar b = 2;\nprint a--b;";
+  for backend in BACKENDS {
+    let options = [&format!("--backend={}", backend), "--stdlib=off"];
+    let (stdout, stderr, code) = run_lox_with("run", "double-negation", src, &options);
+    assert_eq!(
+      (stdout.as_str(), stderr.as_str(), code),
+      ("3\n1\n2\n7\n", "", 0)
+    );
+  }
+
+  let (_, stderr, code) = run_lox("run", "update-errors", "var s = \"a\";\ns++;");
+  assert_eq!(stderr, "Operands must be numbers.\n[line 2]\n");
+  assert_eq!(code, 70);
+}
+
+#[test]
+fn test_ternary_modulo_and_power() {
+  let src = r#"
+    print 7 % 3;
+    print -7 % 3;
+    print 7.5 % 2;
+    print 2 ** 3 ** 2;
+    print -2 ** 2;
+    print 2 ** -1;
+    print 1 + 2 * 3 ** 2 % 5;
+    fun loud(v) { print "evaluated"; return v; }
+    print nil ? loud(1) : false ? loud(2) : "neither";
+    var x = 1 > 0 ? "yes" : loud("no");
+    print x;
+  "#;
+  let (stdout, _, code) = run_lox("run", "ternary", src);
+  assert_eq!(stdout, "1\n-1\n1.5\n512\n-4\n0.5\n4\nneither\nyes\n");
+  assert_eq!(code, 0);
+
+  for src in ["print \"a\" % 2;", "print 2 ** nil;"] {
+    let (_, stderr, code) = run_lox("run", "power-errors", src);
+    assert_eq!(stderr, "Operands must be numbers.\n[line 1]\n");
+    assert_eq!(code, 70);
+  }
+}
+
+#[test]
+fn test_lambdas() {
+  let src = r#"
+    fun compose(f, g) { return fun (x) { return f(g(x)); }; }
+    var inc = fun (x) { return x + 1; };
+    print compose(inc, fun (x) { return x * 2; })(5);
+    var counter = fun () { var i = 0; return fun () { i++; return i; }; }();
+    counter();
+    print counter();
+    fun (n) { print "called with ${n}"; }(3);
+    print inc;
+    var fail = fun () { return nil(); };
+    fail();
+  "#;
+  let (stdout, stderr, code) = run_lox("run", "lambdas", src);
+  assert_eq!(stdout, "11\n2\ncalled with 3\n<fn lambda>\n");
+  assert_eq!(stderr, "Can only call functions and classes.\n[line 9]\n");
+  assert_eq!(code, 70);
+}
@@ -3,7 +3,7 @@ use ember_lox_tokenizer::prelude::*;
 
 #[test]
 fn test_gen_reserved_tok_methods() {
-  let and_tok = Token::or_tok();
+  let and_tok = Token::and_tok();
   assert_eq!(
     and_tok,
     Token {
@@ -11,6 +11,7 @@ fn test_gen_reserved_tok_methods() {
         kind: TokenKind::Identifier,
         len: 3,
         line: 0,
+        span: Span::default(),
       },
       val: "and",
     }
@@ -27,6 +28,7 @@ fn test_gen_reserved_tok_methods_2() {
         kind: TokenKind::Identifier,
         len: 2,
         line: 0,
+        span: Span::default(),
       },
       val: "or",
     }
@@ -0,0 +1,497 @@
+use ember_lox_ast::{ast::prelude::*, visit::VisitorAcceptor, AstPrinter};
+use ember_lox_parse::{
+  error::{Diagnostic, ParseError, RenderMode, Renderer, Severity, Stage},
+  prelude::*,
+};
+
+fn parse_and_print(src: &str) -> Option<Vec<String>> {
+  let mut parser = new_parser_from_src_str(src);
+  let asts = parser.parse().ok()?;
+  let mut printer = AstPrinter;
+  asts
+    .iter()
+    .map(|stmt| stmt.accept(&mut printer))
+    .collect::<Vec<_>>()
+    .into()
+}
+
+#[test]
+fn test_fun_decl_and_return() {
+  let printed = parse_and_print("fun id(x) { return x; } fun noop() { return; }").unwrap();
+  assert_eq!(
+    printed,
+    vec![
+      "(function id(x) ::\n    (return (var x)))",
+      "(function noop() ::\n    (return))",
+    ]
+  );
+}
+
+#[test]
+fn test_return_at_top_level() {
+  // It's rejected by the resolver, not the parser.
+  let printed = parse_and_print("return 1;").unwrap();
+  assert_eq!(printed, vec!["(return 1.0)"]);
+}
+
+#[test]
+fn test_keyword_names_in_declarations() {
+  let cases = [
+    (
+      "fun nil() {}",
+      "[line 1] Error at 'nil': Expect function name.",
+    ),
+    (
+      "fun f(this) {}",
+      "[line 1] Error at 'this': Expect parameter name.",
+    ),
+    (
+      "class print {}",
+      "[line 1] Error at 'print': Expect class name.",
+    ),
+    (
+      "class A < true {}",
+      "[line 1] Error at 'true': Expect superclass name.",
+    ),
+    (
+      "class A { var() {} }",
+      "[line 1] Error at 'var': Expect method name.",
+    ),
+  ];
+  for (src, expected) in cases {
+    let errors = new_parser_from_src_str(src).parse().unwrap_err();
+    let diagnostic = Diagnostic::from(&errors[0]);
+    let rendered = Renderer::new(src, "main.lox", RenderMode::Compat).render(&diagnostic);
+    assert_eq!(rendered, expected, "on `{}`", src);
+  }
+}
+
+#[test]
+fn test_parse_errors() {
+  let src = "var x = @;\n1 = 2;\nprint (1;\nprint 1";
+  let errors = new_parser_from_src_str(src).parse().unwrap_err();
+  assert_eq!(
+    errors,
+    vec![
+      // Lexical errors come first.
+      ParseError::Lexical {
+        error: TokenizationError::UnexpectedCharacter { ch: '@', line: 1 },
+        lexeme: "@".into(),
+        span: Span::new(8, 9, 1, 9),
+      },
+      ParseError::ExpectedExpression {
+        found: Some(";".into()),
+        span: Span::new(9, 10, 1, 10),
+      },
+      ParseError::InvalidAssignmentTarget {
+        found: "=".into(),
+        span: Span::new(13, 14, 2, 3),
+      },
+      ParseError::ExpectedToken {
+        expected: TokenKind::CloseParen,
+        message: "Expect ')' after expression.".into(),
+        found: Some(";".into()),
+        span: Span::new(26, 27, 3, 9),
+      },
+      ParseError::ExpectedToken {
+        expected: TokenKind::Semi,
+        message: "Expect ';' after value.".into(),
+        found: None,
+        span: Span::new(35, 35, 4, 8),
+      },
+    ]
+  );
+  assert_eq!(errors[3].to_string(), "Expect ')' after expression.");
+}
+
+#[test]
+fn test_class_decl_and_property_access() {
+  let printed =
+    parse_and_print("class B < A { get() { return super.get(); } } b.x.y = this;").unwrap();
+  assert_eq!(
+    printed,
+    vec![
+      "(class B extends A ::\n    (function get() ::\n        (return (call (super get) with []))))",
+      "(set (get (var b).x).y <- (this))",
+    ]
+  );
+}
+
+#[test]
+fn test_lists_and_indexing() {
+  let printed = parse_and_print("var xs = [1, [], \"a\"]; xs[0][1] = xs[2];").unwrap();
+  assert_eq!(
+    printed,
+    vec![
+      "(var xs (list [1.0, (list []), a]))",
+      "(set (get (var xs)[0.0])[1.0] <- (get (var xs)[2.0]))",
+    ]
+  );
+  let errors = new_parser_from_src_str("xs[0;").parse().unwrap_err();
+  assert_eq!(errors[0].to_string(), "Expect ']' after index.");
+}
+
+#[test]
+fn test_maps_and_blocks() {
+  let printed = parse_and_print("{ var m = {\"a\": {}, 1: nil}; }").unwrap();
+  assert_eq!(
+    printed,
+    vec!["(block ::\n    (var m (map [a: (map []), 1.0: nil])))"]
+  );
+  let errors = new_parser_from_src_str("var m = {\"a\" 1};")
+    .parse()
+    .unwrap_err();
+  assert_eq!(errors[0].to_string(), "Expect ':' after map key.");
+}
+
+#[test]
+fn test_spans() {
+  let src = "var é = 1;\nprint é +\n  \"ab\";";
+  let tokens = tag_to_named_tokens(src, tokenize(src))
+    .filter(|t| !matches!(t.tag.kind, TokenKind::Whitespace | TokenKind::NewLine))
+    .collect::<Vec<_>>();
+  // `é` takes 2 bytes, but only 1 column.
+  assert_eq!(tokens[1].tag.span, Span::new(4, 6, 1, 5));
+  assert_eq!(tokens[2].tag.span, Span::new(7, 8, 1, 7));
+  assert_eq!(tokens[6].tag.span, Span::new(18, 20, 2, 7));
+  assert_eq!(&src[tokens[6].tag.span.start..tokens[6].tag.span.end], "é");
+
+  let stmts = new_parser_from_src_str(src).parse().unwrap();
+  let print = &stmts[1];
+  assert_eq!(print.span(), Span::new(12, 30, 2, 1));
+  let Stmt::Print { expr, .. } = print else {
+    panic!("expect a print statement, got {:?}", print);
+  };
+  // The binary expression spans over two lines, from `é` to `"ab"`.
+  assert_eq!(expr.span(), Span::new(18, 29, 2, 7));
+  assert_eq!(expr.pos(), Span::new(21, 22, 2, 9));
+}
+
+#[test]
+fn test_severity() {
+  let src = "var a = 1;";
+  let mut diagnostic = Diagnostic::at(Some("a"), "Unused variable.", Span::new(4, 5, 1, 5));
+  assert!(diagnostic.is_error());
+  let rich = Renderer::new(src, "main.lox", RenderMode::Rich);
+  let compat = Renderer::new(src, "main.lox", RenderMode::Compat);
+  assert!(rich
+    .render(&diagnostic)
+    .starts_with("error: Unused variable.\n"));
+  assert_eq!(
+    compat.render(&diagnostic),
+    "[line 1] Error at 'a': Unused variable."
+  );
+
+  diagnostic.severity = Severity::Warning;
+  assert!(!diagnostic.is_error());
+  assert_eq!(
+    rich.render(&diagnostic),
+    "warning: Unused variable.\n --> main.lox:1:5\n  |\n1 | var a = 1;\n  |     ^"
+  );
+  assert_eq!(
+    compat.render(&diagnostic),
+    "[line 1] Warning at 'a': Unused variable."
+  );
+}
+
+#[test]
+fn test_numeric_literals() {
+  let src = "1_000 0xff 0b1010 0o17 1e3 2.5E-3 0x_FF_FF 0XfF 0B11 0O17";
+  let values = tag_to_named_tokens(src, tokenize(src))
+    .filter(|t| {
+      t.tag.kind
+        == TokenKind::Literal {
+          kind: LiteralKind::Number,
+        }
+    })
+    .map(|t| parse_number(t.val))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    values,
+    vec![1000.0, 255.0, 10.0, 15.0, 1000.0, 0.0025, 65535.0, 255.0, 3.0, 15.0]
+  );
+
+  let errors = new_parser_from_src_str("0x; 0b102; 1e+;")
+    .parse()
+    .unwrap_err();
+  assert_eq!(
+    errors[0],
+    ParseError::Lexical {
+      error: TokenizationError::EmptyDigits {
+        base: Base::Hexadecimal,
+        line: 1
+      },
+      lexeme: "0x".into(),
+      span: Span::new(0, 2, 1, 1),
+    }
+  );
+  // Lexical errors come first.
+  let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+  assert_eq!(
+    messages[..3],
+    vec![
+      "Expect hexadecimal digits after '0x'.",
+      "Invalid digit '2' in binary literal 0b102.",
+      "Expect digits in the exponent of 1e+.",
+    ]
+  );
+
+  // Separators must be followed by a digit.
+  let errors = new_parser_from_src_str("1_; 1_.5; 1e5_; 0XFF_; 0X;")
+    .parse()
+    .unwrap_err();
+  assert_eq!(
+    errors[0],
+    ParseError::Lexical {
+      error: TokenizationError::TrailingSeparator {
+        base: Base::Decimal,
+        line: 1
+      },
+      lexeme: "1_".into(),
+      span: Span::new(0, 2, 1, 1),
+    }
+  );
+  let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+  assert_eq!(
+    messages[..5],
+    vec![
+      "Trailing '_' in decimal literal 1_.",
+      "Trailing '_' in decimal literal 1_.5.",
+      "Trailing '_' in decimal literal 1e5_.",
+      "Trailing '_' in hexadecimal literal 0XFF_.",
+      "Expect hexadecimal digits after '0X'.",
+    ]
+  );
+}
+
+#[test]
+fn test_string_escapes_and_lines() {
+  let printed = parse_and_print(r#"print "a\tb\\ \"c\"\n\u{e9}\0";"#).unwrap();
+  assert_eq!(printed, vec!["(print a\tb\\ \"c\"\n\u{e9}\0)"]);
+
+  // Values are printed as they are, e.g. a literal tab or a multi-line string.
+  let src = "\"a\tb\" \"c\nd\" \"\\u{e9}\"";
+  let printed = tag_to_named_tokens(src, tokenize(src))
+    .filter(|t| t.tag.kind != TokenKind::Whitespace)
+    .map(|t| t.dbg())
+    .collect::<Vec<_>>();
+  assert_eq!(
+    printed,
+    vec![
+      "STRING \"a\tb\" a\tb",
+      "STRING \"c\nd\" c\nd",
+      "STRING \"\\u{e9}\" é"
+    ]
+  );
+
+  // Tokens after a multi-line string are on the right line.
+  let src = "print \"a\nb\";\nprint x";
+  let tokens = tag_to_named_tokens(src, tokenize(src)).collect::<Vec<_>>();
+  let x = tokens.last().unwrap();
+  assert_eq!((x.val, x.tag.line), ("x", 3));
+
+  let errors = new_parser_from_src_str("\"\\q\";\n\"\\u{110000}\";\n\"\n\\q")
+    .parse()
+    .unwrap_err();
+  // Lexical errors come first.
+  let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+  assert_eq!(
+    messages[..3],
+    vec![
+      "Invalid escape sequence: \\q",
+      "Invalid escape sequence: \\u",
+      "Unterminated string.",
+    ]
+  );
+  // It starts on line 3, but the tokenizer stops on line 4.
+  let diagnostic = Diagnostic::from(&errors[2]);
+  assert_eq!(diagnostic.span.line, 3);
+  assert_eq!(diagnostic.stage, Stage::Lexical { line: 4 });
+}
+
+#[test]
+fn test_string_interpolation() {
+  let printed = parse_and_print(r#"print "a ${x} b ${ {"k": "${1}"}["k"] }";"#).unwrap();
+  assert_eq!(
+    printed,
+    vec!["(print (interpolate [a , (var x),  b , (get (map [k: (interpolate [1.0])])[k])]))"]
+  );
+  // An escaped `$` starts no interpolation.
+  let printed = parse_and_print(r#"print "\${x}";"#).unwrap();
+  assert_eq!(printed, vec!["(print ${x})"]);
+
+  let src = r#""a${b}c${d}e""#;
+  let kinds = tag_to_named_tokens(src, tokenize(src))
+    .map(|t| t.dbg())
+    .collect::<Vec<_>>();
+  assert_eq!(
+    kinds,
+    vec![
+      "STRING_HEAD \"a${ a",
+      "IDENTIFIER b null",
+      "STRING_MIDDLE }c${ c",
+      "IDENTIFIER d null",
+      "STRING_TAIL }e\" e",
+    ]
+  );
+
+  let errors = new_parser_from_src_str(r#"print "a ${x y}";"#)
+    .parse()
+    .unwrap_err();
+  assert_eq!(errors[0].to_string(), "Expect '}' after interpolation.");
+}
+
+#[test]
+fn test_block_comments() {
+  let src = "/* a /* nested\n */ still */ print 1 /* */;\n/**/ print x;";
+  let printed = parse_and_print(src).unwrap();
+  assert_eq!(printed, vec!["(print 1.0)", "(print (var x))"]);
+  // Lines inside comments are counted.
+  let tokens = tag_to_named_tokens(src, tokenize(src)).collect::<Vec<_>>();
+  let x = tokens.iter().rfind(|t| t.val == "x").unwrap();
+  assert_eq!(x.tag.line, 3);
+
+  let errors = new_parser_from_src_str("print 1;\n/* a /* b */\n")
+    .parse()
+    .unwrap_err();
+  assert_eq!(errors[0].to_string(), "Unterminated block comment.");
+  assert_eq!(
+    Diagnostic::from(&errors[0]).stage,
+    Stage::Lexical { line: 3 }
+  );
+}
+
+#[test]
+fn test_loop_control() {
+  let printed = parse_and_print("for (;;) { if (a) break; continue; }").unwrap();
+  assert_eq!(
+    printed,
+    vec!["(for _ true _ ::\n    (block ::\n        (if (var a) then ::\n            (break))\n        (continue)))"]
+  );
+
+  // A function body is outside of the enclosing loop.
+  let src = "break;\nwhile (true) { fun f() { continue; } }\nwhile (true) break";
+  let errors = new_parser_from_src_str(src).parse().unwrap_err();
+  let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+  assert_eq!(
+    messages,
+    vec![
+      "Can't use 'break' outside of a loop.",
+      "Can't use 'continue' outside of a loop.",
+      "Expect ';' after 'break'.",
+    ]
+  );
+  assert_eq!(errors[1].span().line, 2);
+}
+
+#[test]
+fn test_compound_assignment_and_increments() {
+  let printed = parse_and_print("a += b -= 2; o.f *= 3; l[i] /= 4; ++a; l[0]--; -a++;").unwrap();
+  assert_eq!(
+    printed,
+    vec![
+      "(+= (var a) (-= (var b) 2.0))",
+      "(*= (get (var o).f) 3.0)",
+      "(/= (get (var l)[(var i)]) 4.0)",
+      "(++ (var a))",
+      "(post-- (get (var l)[0.0]))",
+      "(- (post++ (var a)))",
+    ]
+  );
+
+  let src = "a+++b";
+  let tokens = tag_to_named_tokens(src, tokenize(src))
+    .map(|t| t.dbg())
+    .collect::<Vec<_>>();
+  assert_eq!(
+    tokens,
+    vec![
+      "IDENTIFIER a null",
+      "PLUS_PLUS ++ null",
+      "PLUS + null",
+      "IDENTIFIER b null"
+    ]
+  );
+
+  // `--` that can't be a decrement is two `-`, as in plain Lox.
+  let printed =
+    parse_and_print("--(3); --1; 1--1; 1--1*2; (a)--b; a--b; a--(1); a-- - b;").unwrap();
+  assert_eq!(
+    printed,
+    vec![
+      "(- (- (group 3.0)))",
+      "(- (- 1.0))",
+      "(- 1.0 (- 1.0))",
+      "(- 1.0 (* (- 1.0) 2.0))",
+      "(- (group (var a)) (- (var b)))",
+      "(- (var a) (- (var b)))",
+      "(- (var a) (- (group 1.0)))",
+      "(- (post-- (var a)) (var b))",
+    ]
+  );
+
+  let errors = new_parser_from_src_str("1 += 2;\n++(a);\n(a)++;")
+    .parse()
+    .unwrap_err();
+  let found = errors
+    .iter()
+    .map(|e| (e.to_string(), e.found().unwrap().to_string()))
+    .collect::<Vec<_>>();
+  let invalid = "Invalid assignment target.".to_string();
+  assert_eq!(
+    found,
+    vec![
+      (invalid.clone(), "+=".to_string()),
+      (invalid.clone(), "++".to_string()),
+      (invalid, "++".to_string()),
+    ]
+  );
+}
+
+#[test]
+fn test_ternary_modulo_and_power() {
+  let printed = parse_and_print("a = b ? c : d ? e : f; -2 ** 3 ** x % 4;").unwrap();
+  assert_eq!(
+    printed,
+    vec![
+      "(assign a (?: (var b) (var c) (?: (var d) (var e) (var f))))",
+      "(% (- (** 2.0 (** 3.0 (var x)))) 4.0)",
+    ]
+  );
+
+  let errors = new_parser_from_src_str("a ? b;\na ? b : c = d;")
+    .parse()
+    .unwrap_err();
+  let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+  assert_eq!(
+    messages,
+    vec![
+      "Expect ':' after then branch of '?'.",
+      "Invalid assignment target.",
+    ]
+  );
+}
+
+#[test]
+fn test_lambdas() {
+  let printed = parse_and_print("var f = fun (a, b) { return a; };\nfun () {}();").unwrap();
+  assert_eq!(
+    printed,
+    vec![
+      "(var f (function (a, b) ::\n    (return (var a))))",
+      "(call (function () ) with [])",
+    ]
+  );
+
+  let errors = new_parser_from_src_str("fun {}\nwhile (true) fun () { continue; };")
+    .parse()
+    .unwrap_err();
+  let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+  assert_eq!(
+    messages,
+    vec![
+      "Expect '(' after 'fun'.",
+      "Can't use 'continue' outside of a loop.",
+    ]
+  );
+}
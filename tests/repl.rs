@@ -0,0 +1,83 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Feeds `input` to the `REPL`, returns `(stdout, stderr, exit_code)`.
+fn run_repl(args: &[&str], input: &str) -> (String, String, i32) {
+  let mut child = Command::new(env!("CARGO_BIN_EXE_ember-lox"))
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  child
+    .stdin
+    .take()
+    .unwrap()
+    .write_all(input.as_bytes())
+    .unwrap();
+  let output = child.wait_with_output().unwrap();
+  (
+    String::from_utf8(output.stdout).unwrap(),
+    String::from_utf8(output.stderr).unwrap(),
+    output.status.code().unwrap_or(-1),
+  )
+}
+
+#[test]
+fn test_repl_keeps_state_across_inputs() {
+  let input = "var a = 1\n\
+               fun add(x,\n  y) {\n  return x + y; // )\n}\n\
+               1 +;\n\
+               print add(a, 2);\n\
+               a = a + 1\n\
+               a\n";
+  for backend in ["--backend=tree", "--backend=vm"] {
+    let (stdout, stderr, code) = run_repl(&[backend, "--diagnostics=compat"], input);
+    assert_eq!(stdout, "3\n2\n2\n");
+    // Lines are counted across the whole session.
+    assert_eq!(stderr, "[line 6] Error at ';': Expect expression.\n");
+    assert_eq!(code, 0);
+  }
+}
+
+#[test]
+fn test_repl_recovers_from_runtime_errors() {
+  let input = "class A { init(n) { this.n = n; } }\nnil()\nA(3).n\n";
+  for args in [&["repl"][..], &["repl", "--backend=vm"]] {
+    let (stdout, stderr, code) = run_repl(args, input);
+    // Only top-level expressions are printed, not those in `init`.
+    assert_eq!(stdout, "3\n");
+    assert_eq!(
+      stderr,
+      "error: Can only call functions and classes.\n --> <repl>:2:1\n  |\n2 | nil();\n  | ^^^\n"
+    );
+    assert_eq!(code, 0);
+  }
+}
+
+#[test]
+fn test_repl_waits_for_block_comments() {
+  let input = "print 1; /* (\n/* nested */ still ( */ print 2;\n";
+  let (stdout, stderr, code) = run_repl(&["--diagnostics=compat"], input);
+  assert_eq!(stdout, "1\n2\n");
+  assert_eq!(stderr, "");
+  assert_eq!(code, 0);
+}
+
+#[test]
+fn test_repl_tracks_interpolations() {
+  // Quotes and brackets in the strings of an interpolation don't count.
+  let input = r#"print "${"x"}";
+print "${ "y" +
+"}" }";
+print "a${"(" }b";
+print 1;
+"#;
+  for backend in ["--backend=tree", "--backend=vm"] {
+    let (stdout, stderr, code) = run_repl(&[backend, "--diagnostics=compat"], input);
+    assert_eq!(stdout, "x\ny}\na(b\n1\n");
+    assert_eq!(stderr, "");
+    assert_eq!(code, 0);
+  }
+}
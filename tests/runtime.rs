@@ -0,0 +1,120 @@
+use ember_lox_ast::ast::prelude::OperandError;
+use ember_lox_parse::prelude::*;
+use ember_lox_rt::{
+  error::{ErrorKind, RuntimeError},
+  native::NativeFunction,
+  resolver::Resolver,
+  runtime::{Backend, Runtime},
+  value::Value,
+};
+use std::{cell::RefCell, rc::Rc};
+
+fn interpret_with(runtime: &mut dyn Runtime, src: &str) -> Result<(), RuntimeError> {
+  let asts = new_parser_from_src_str(src).parse().unwrap();
+  Resolver::default().resolve(&asts).unwrap();
+  runtime.interpret(&asts, false)
+}
+
+fn interpret(backend: Backend, src: &str) -> Result<(), RuntimeError> {
+  interpret_with(backend.runtime().as_mut(), src)
+}
+
+/// Fails in `inner`, called by `outer`, called by the script.
+const NESTED_FAILURE: &str =
+  "fun inner(a) {\n  return a + nil;\n}\nfun outer() {\n  inner(1);\n}\nouter();";
+
+#[test]
+fn test_runtime_error() {
+  for backend in [Backend::Tree, Backend::Vm] {
+    let error = interpret(backend, NESTED_FAILURE).unwrap_err();
+    assert_eq!(
+      error.kind,
+      ErrorKind::TypeMismatch(OperandError::ExpectNumbers)
+    );
+    assert_eq!(error.to_string(), "Operands must be numbers.");
+    assert_eq!((error.span.line, error.span.col), (2, 12));
+
+    // Innermost first, each with the line it's executing.
+    let trace = error
+      .trace
+      .iter()
+      .map(|frame| (frame.function.as_deref(), frame.span.line))
+      .collect::<Vec<_>>();
+    assert_eq!(trace, [(Some("inner"), 2), (Some("outer"), 5), (None, 7)]);
+  }
+}
+
+#[test]
+fn test_stack_trace_lines() {
+  for backend in [Backend::Tree, Backend::Vm] {
+    let error = interpret(backend, NESTED_FAILURE).unwrap_err();
+    let lines = error
+      .trace
+      .iter()
+      .map(ToString::to_string)
+      .collect::<Vec<_>>();
+    assert_eq!(
+      lines,
+      [
+        "[line 2] in inner()",
+        "[line 5] in outer()",
+        "[line 7] in script"
+      ]
+    );
+  }
+}
+
+#[test]
+fn test_runtime_error_kinds() {
+  let cases = [
+    ("print x;", ErrorKind::UndefinedVariable("x".into())),
+    ("nil();", ErrorKind::NotCallable),
+    (
+      "class A {} A().m;",
+      ErrorKind::UndefinedProperty("m".into()),
+    ),
+    ("1 .x = 2;", ErrorKind::NoFields),
+    (
+      "fun f(a) {} f();",
+      ErrorKind::ArityMismatch {
+        expected: 1,
+        found: 0,
+      },
+    ),
+    ("fun f() { f(); } f();", ErrorKind::StackOverflow),
+  ];
+  for (src, kind) in cases {
+    for backend in [Backend::Tree, Backend::Vm] {
+      assert_eq!(interpret(backend, src).unwrap_err().kind, kind, "`{}`", src);
+    }
+  }
+}
+
+#[test]
+fn test_host_natives() {
+  for backend in [Backend::Tree, Backend::Vm] {
+    let mut runtime = backend.runtime();
+    let logged = Rc::new(RefCell::new(vec![]));
+    let log = logged.clone();
+    runtime.define_native(NativeFunction::new("log", 1, move |args| {
+      log.borrow_mut().push(args[0].to_string());
+      Ok(Value::nil())
+    }));
+    runtime.define_native(NativeFunction::new("fail", 0, |_| {
+      Err("Host failure.".to_string())
+    }));
+
+    let src = "fun twice(x) { return x * 2; }\nlog(twice(21));\nlog(clock() > 0);\nlog(log);";
+    interpret_with(runtime.as_mut(), src).unwrap();
+    assert_eq!(*logged.borrow(), ["42", "true", "<native fn>"]);
+
+    let error = interpret_with(runtime.as_mut(), "fun f() {\n  fail();\n}\nf();").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Native("Host failure.".into()));
+    let trace = error
+      .trace
+      .iter()
+      .map(|frame| (frame.function.as_deref(), frame.span.line))
+      .collect::<Vec<_>>();
+    assert_eq!(trace, [(Some("f"), 2), (None, 4)]);
+  }
+}
@@ -23,5 +23,5 @@ set -e # Exit early if any commands fail
 # - Edit .codecrafters/run.sh to change how your program runs remotely
 (
     cd "$(dirname "$0")"
-    exec ./target/release/ember-lox "$@"
+    exec ./target/release/ember-lox "$@" --diagnostics=compat --stdlib=off
 )
\ No newline at end of file